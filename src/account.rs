@@ -0,0 +1,190 @@
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+/// Shortest and longest passwords accepted, in characters
+pub const MIN_PASSWORD_LEN: usize = 8;
+pub const MAX_PASSWORD_LEN: usize = 128;
+/// Wrong passwords in a row before the account is locked
+pub const MAX_FAILED_LOGINS: u32 = 5;
+/// How long a locked account refuses logins
+pub const LOCKOUT: Duration = Duration::from_secs(300);
+
+/// A user's login details: the salted password hash and recent failed attempts
+#[derive(Debug, Clone)]
+pub struct Account {
+    /// Argon2id hash in PHC string form, with its salt and parameters
+    password_hash: String,
+    /// Wrong passwords since the last successful login or lockout
+    failed_logins: u32,
+    /// Logins are refused until then
+    locked_until: Option<Instant>,
+}
+
+impl Account {
+    pub fn new(password_hash: String) -> Self {
+        Account { password_hash, failed_logins: 0, locked_until: None }
+    }
+
+    pub fn password_hash(&self) -> &str {
+        &self.password_hash
+    }
+
+    pub fn set_password_hash(&mut self, password_hash: String) {
+        self.password_hash = password_hash;
+    }
+
+    /// Fails while the account is locked, saying how long is left
+    pub fn check_unlocked(&self, now: Instant) -> Result<(), String> {
+        match self.locked_until {
+            Some(until) if until > now => {
+                Err(format!("Too many failed logins; try again in {} seconds", (until - now).as_secs().max(1)))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Records whether a password check passed, locking the account after too many
+    /// failures in a row
+    pub fn record_attempt(&mut self, passed: bool, now: Instant) -> Result<(), String> {
+        self.check_unlocked(now)?;
+        if passed {
+            self.failed_logins = 0;
+            return Ok(());
+        }
+        self.failed_logins += 1;
+        if self.failed_logins >= MAX_FAILED_LOGINS {
+            self.failed_logins = 0;
+            self.locked_until = Some(now + LOCKOUT);
+            return self.check_unlocked(now);
+        }
+        Err("Wrong name or password".to_string())
+    }
+}
+
+/// Checks a new password's length and hashes it with Argon2id and a fresh random salt
+pub fn hash_password(password: &str) -> Result<String, String> {
+    let len = password.chars().count();
+    if len < MIN_PASSWORD_LEN {
+        return Err(format!("Passwords must be at least {} characters", MIN_PASSWORD_LEN));
+    }
+    if len > MAX_PASSWORD_LEN {
+        return Err(format!("Passwords can be at most {} characters", MAX_PASSWORD_LEN));
+    }
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| format!("Could not hash password: {}", e))
+}
+
+/// Whether the password matches a hash made by `hash_password`
+pub fn verify_password(password_hash: &str, password: &str) -> bool {
+    PasswordHash::new(password_hash)
+        .is_ok_and(|hash| Argon2::default().verify_password(password.as_bytes(), &hash).is_ok())
+}
+
+/// Hash of a random password, checked in place of a real one when a login names an
+/// unknown user so the reply takes as long as for a user who exists
+pub fn dummy_hash() -> &'static str {
+    static HASH: OnceLock<String> = OnceLock::new();
+    HASH.get_or_init(|| {
+        let password = SaltString::generate(&mut OsRng);
+        let salt = SaltString::generate(&mut OsRng);
+        Argon2::default()
+            .hash_password(password.as_str().as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .expect("Argon2 with its default parameters hashes any short password")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+
+    #[test]
+    fn test_passwords_are_salted_and_verified() {
+        let hash = hash_password("correct horse").unwrap();
+        assert!(hash.starts_with("$argon2id$"));
+        assert!(verify_password(&hash, "correct horse"));
+        assert!(!verify_password(&hash, "correct horsE"));
+        assert_ne!(hash, hash_password("correct horse").unwrap());
+        assert!(hash_password("short").is_err());
+        assert!(hash_password(&"a".repeat(MAX_PASSWORD_LEN + 1)).is_err());
+    }
+
+    #[test]
+    fn test_dummy_hash_is_a_real_hash_that_matches_nothing() {
+        assert!(dummy_hash().starts_with("$argon2id$"));
+        assert!(PasswordHash::new(dummy_hash()).is_ok());
+        assert!(!verify_password(dummy_hash(), "correct horse"));
+        assert_eq!(dummy_hash(), dummy_hash());
+    }
+
+    #[test]
+    fn test_repeated_failures_lock_the_account() {
+        let mut account = Account::new(String::new());
+        let now = Instant::now();
+        for _ in 1..MAX_FAILED_LOGINS {
+            assert_eq!(account.record_attempt(false, now).unwrap_err(), "Wrong name or password");
+        }
+        // A success resets the count
+        account.record_attempt(true, now).unwrap();
+        for _ in 1..MAX_FAILED_LOGINS {
+            account.record_attempt(false, now).unwrap_err();
+        }
+        assert!(account.record_attempt(false, now).unwrap_err().starts_with("Too many failed logins"));
+        // Even the right password is refused until the lockout ends
+        assert!(account.record_attempt(true, now + LOCKOUT / 2).is_err());
+        account.record_attempt(true, now + LOCKOUT).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_login_needs_the_password_and_locks_after_repeated_failures() {
+        let url = start_gateway().await;
+        let (mut alice, _) = connect_async(&url).await.unwrap();
+        alice.send(Message::text("PROTOCOL json")).await.unwrap();
+        alice.send(Message::text(r#"{"type":"register","name":"alice","password":"hunter22"}"#)).await.unwrap();
+        let registered: serde_json::Value = serde_json::from_str(&wait_for(&mut alice, "registered").await).unwrap();
+        alice.send(Message::text(r#"{"type":"change_password","old":"hunter22","new":"hunter2222"}"#)).await.unwrap();
+        let changed: serde_json::Value = serde_json::from_str(&wait_for(&mut alice, "password_changed").await).unwrap();
+        assert_ne!(changed["token"], registered["token"]);
+        // The token from before the change no longer resumes the account
+        let mut thief = json_client(&url, "mallory").await;
+        thief.send(Message::text(format!(r#"{{"type":"resume","token":{}}}"#, registered["token"]))).await.unwrap();
+        wait_for(&mut thief, "Unknown or expired session token").await;
+
+        let (mut stream, _) = connect_async(&url).await.unwrap();
+        stream.send(Message::text("PROTOCOL text")).await.unwrap();
+        stream.send(Message::text("REGISTER alice whatever1")).await.unwrap();
+        wait_for(&mut stream, "Username already taken").await;
+        stream.send(Message::text("LOGIN alice hunter22")).await.unwrap();
+        wait_for(&mut stream, "Wrong name or password").await;
+        stream.send(Message::text("LOGIN alice hunter2222")).await.unwrap();
+        wait_for(&mut stream, "Welcome back, alice. Your balance: 100\nSession token: ").await;
+        // The login took the session over from the first connection
+        alice.send(Message::text(r#"{"type":"show"}"#)).await.unwrap();
+        wait_for(&mut alice, "resumed on another connection").await;
+        // A logged-in connection can not log in or register again
+        stream.send(Message::text("LOGIN alice hunter2222")).await.unwrap();
+        wait_for(&mut stream, "You are already logged in").await;
+        stream.send(Message::text("REGISTER carol hunter22")).await.unwrap();
+        wait_for(&mut stream, "You are already logged in").await;
+
+        let (mut stream, _) = connect_async(&url).await.unwrap();
+        stream.send(Message::text("PROTOCOL text")).await.unwrap();
+        stream.send(Message::text("LOGIN nobody hunter22")).await.unwrap();
+        wait_for(&mut stream, "Wrong name or password").await;
+        for _ in 1..MAX_FAILED_LOGINS {
+            stream.send(Message::text("LOGIN alice nope-nope")).await.unwrap();
+            wait_for(&mut stream, "Wrong name or password").await;
+        }
+        stream.send(Message::text("LOGIN alice nope-nope")).await.unwrap();
+        wait_for(&mut stream, "Too many failed logins").await;
+        stream.send(Message::text("LOGIN alice hunter2222")).await.unwrap();
+        wait_for(&mut stream, "Too many failed logins").await;
+    }
+}
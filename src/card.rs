@@ -1,3 +1,7 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Suit {
     Clubs,
@@ -21,10 +25,116 @@ pub enum Rank {
     Queen,
     King,
     Ace,
+    /// A joker; the deck's two jokers are told apart by suit (spades and hearts)
+    Joker,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Card {
     pub rank: Rank,
     pub suit: Suit,
-}
\ No newline at end of file
+}
+
+impl Rank {
+    /// Single character used when printing hands, e.g. `A`, `T`, `7`
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Rank::Two => "2",
+            Rank::Three => "3",
+            Rank::Four => "4",
+            Rank::Five => "5",
+            Rank::Six => "6",
+            Rank::Seven => "7",
+            Rank::Eight => "8",
+            Rank::Nine => "9",
+            Rank::Ten => "T",
+            Rank::Jack => "J",
+            Rank::Queen => "Q",
+            Rank::King => "K",
+            Rank::Ace => "A",
+            Rank::Joker => "X",
+        }
+    }
+}
+
+impl fmt::Display for Card {
+    /// Short notation such as `As` or `Td`, the same form `parse` accepts
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let suit = match self.suit {
+            Suit::Clubs => "c",
+            Suit::Diamonds => "d",
+            Suit::Hearts => "h",
+            Suit::Spades => "s",
+        };
+        write!(f, "{}{}", self.rank.symbol(), suit)
+    }
+}
+
+impl FromStr for Card {
+    type Err = String;
+
+    /// Parses short notation: a rank (`2`-`9`, `T` or `10`, `J`, `Q`, `K`, `A`,
+    /// or `X` for a joker) followed by a suit (`c`, `d`, `h`, `s`), e.g. `As`, `10h`, `Xs`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.to_ascii_uppercase();
+        if s.len() < 2 {
+            return Err(format!("Invalid card: {}", s));
+        }
+        let (rank_str, suit_str) = s.split_at(s.len() - 1);
+        let rank = match rank_str {
+            "2" => Rank::Two,
+            "3" => Rank::Three,
+            "4" => Rank::Four,
+            "5" => Rank::Five,
+            "6" => Rank::Six,
+            "7" => Rank::Seven,
+            "8" => Rank::Eight,
+            "9" => Rank::Nine,
+            "T" | "10" => Rank::Ten,
+            "J" => Rank::Jack,
+            "Q" => Rank::Queen,
+            "K" => Rank::King,
+            "A" => Rank::Ace,
+            "X" => Rank::Joker,
+            _ => return Err(format!("Invalid card: {}", s)),
+        };
+        let suit = match suit_str {
+            "C" => Suit::Clubs,
+            "D" => Suit::Diamonds,
+            "H" => Suit::Hearts,
+            "S" => Suit::Spades,
+            _ => return Err(format!("Invalid card: {}", s)),
+        };
+        Ok(Card { rank, suit })
+    }
+}
+
+/// Cards are sent over the wire in the same short notation, e.g. `"As"`
+impl Serialize for Card {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Card {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_card_notation_round_trips() {
+        for text in ["As", "Td", "2c", "Kh"] {
+            let card: Card = text.parse().unwrap();
+            assert_eq!(card.to_string(), text);
+        }
+        assert_eq!("10h".parse::<Card>().unwrap(), Card { rank: Rank::Ten, suit: Suit::Hearts });
+        assert!("1x".parse::<Card>().is_err());
+        assert_eq!("Xh".parse::<Card>().unwrap(), Card { rank: Rank::Joker, suit: Suit::Hearts });
+    }
+}
+
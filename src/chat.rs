@@ -0,0 +1,106 @@
+use std::fmt;
+
+/// Longest chat message allowed, in characters
+pub const MAX_CHAT_LEN: usize = 200;
+
+/// Words masked by the filter installed when the server starts
+pub const DEFAULT_BLOCKED_WORDS: &[&str] = &["damn", "shit", "fuck", "bastard", "asshole"];
+
+/// A server-side check run on every chat message before it is delivered. A filter
+/// can rewrite the text (e.g. mask words) or reject the message with a reason.
+pub trait ChatFilter: fmt::Debug + Send + Sync {
+    fn filter(&self, text: &str) -> Result<String, String>;
+}
+
+/// Masks listed words with asterisks, ignoring case
+#[derive(Debug, Clone, Default)]
+pub struct WordFilter {
+    words: Vec<String>,
+}
+
+impl WordFilter {
+    pub fn new(words: &[&str]) -> Self {
+        WordFilter { words: words.iter().map(|w| w.to_lowercase()).collect() }
+    }
+}
+
+impl ChatFilter for WordFilter {
+    fn filter(&self, text: &str) -> Result<String, String> {
+        let masked: Vec<String> = text.split(' ')
+            .map(|word| {
+                let bare = word.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase();
+                if self.words.contains(&bare) {
+                    word.chars().map(|c| if c.is_alphanumeric() { '*' } else { c }).collect()
+                } else {
+                    word.to_string()
+                }
+            })
+            .collect();
+        Ok(masked.join(" "))
+    }
+}
+
+/// Checks a chat message's length and runs it through every filter in turn,
+/// returning the text to deliver
+pub fn prepare_message(filters: &[Box<dyn ChatFilter>], text: &str) -> Result<String, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("Cannot send an empty message".to_string());
+    }
+    if text.chars().count() > MAX_CHAT_LEN {
+        return Err(format!("Messages can be at most {} characters", MAX_CHAT_LEN));
+    }
+    filters.iter().try_fold(text.to_string(), |text, filter| filter.filter(&text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+
+    /// Rejects messages that shout
+    #[derive(Debug)]
+    struct NoShouting;
+
+    impl ChatFilter for NoShouting {
+        fn filter(&self, text: &str) -> Result<String, String> {
+            if text.chars().any(|c| c.is_lowercase()) {
+                Ok(text.to_string())
+            } else {
+                Err("No shouting".to_string())
+            }
+        }
+    }
+
+    #[test]
+    fn test_chat_messages_are_limited_and_filtered() {
+        let filters: Vec<Box<dyn ChatFilter>> = vec![Box::new(WordFilter::new(&["darn"])), Box::new(NoShouting)];
+        assert_eq!(prepare_message(&filters, "  nice hand  ").unwrap(), "nice hand");
+        assert_eq!(prepare_message(&filters, "Darn, a river card!").unwrap(), "****, a river card!");
+        assert_eq!(prepare_message(&filters, "ALL IN").unwrap_err(), "No shouting");
+        assert!(prepare_message(&filters, "   ").is_err());
+        assert!(prepare_message(&filters, &"a".repeat(MAX_CHAT_LEN + 1)).is_err());
+        assert!(prepare_message(&filters, &"a".repeat(MAX_CHAT_LEN)).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_chat_reaches_the_lobby_and_whispers_respect_mutes() {
+        let url = start_gateway().await;
+        let mut alice = json_client(&url, "alice").await;
+        let mut bob = json_client(&url, "bob").await;
+        alice.send(Message::text(r#"{"type":"say","text":"hello lobby"}"#)).await.unwrap();
+        let chat = wait_for(&mut bob, r#""type":"chat""#).await;
+        assert!(chat.contains(r#""scope":"lobby""#) && chat.contains("hello lobby"));
+
+        bob.send(Message::text(r#"{"type":"mute","user":"alice"}"#)).await.unwrap();
+        wait_for(&mut bob, "Muted alice").await;
+        alice.send(Message::text(r#"{"type":"whisper","to":"bob","text":"psst"}"#)).await.unwrap();
+        wait_for(&mut alice, "Whisper sent to bob").await;
+        bob.send(Message::text(r#"{"type":"unmute","user":"alice"}"#)).await.unwrap();
+        wait_for(&mut bob, "Unmuted alice").await;
+        alice.send(Message::text(r#"{"type":"whisper","to":"bob","text":"again"}"#)).await.unwrap();
+        // The muted whisper was never delivered, so the next chat bob sees is the second one
+        let chat = wait_for(&mut bob, r#""type":"chat""#).await;
+        assert!(chat.contains(r#""scope":"whisper""#) && chat.contains("again"));
+    }
+}
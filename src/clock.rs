@@ -0,0 +1,237 @@
+use crate::commands::advance_game;
+use crate::models::*;
+use crate::protocol::{ServerMessage, TableEvent};
+use crate::utils::{broadcast_game_state, send_to_players};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Seconds left on the clock or time bank when the table is warned
+const WARNING_SECS: u64 = 5;
+
+/// The clock running at a table for the current turn. Dropping it stops the clock,
+/// so replacing a table's clock cancels the old one.
+#[derive(Debug)]
+pub struct ActionClock {
+    turn: (usize, usize, usize),
+    handle: JoinHandle<()>,
+}
+
+impl Drop for ActionClock {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+/// Makes sure the table's clock is running for the current turn: keeps it if the turn
+/// has not changed, starts a fresh one if it has, and stops it when nobody is to act.
+pub fn ensure_clock(state: &Arc<Mutex<ServerState>>, table_id: &TableId) {
+    let mut guard = state.lock().unwrap();
+    let Some(table) = guard.tables.get_mut(table_id) else {
+        return;
+    };
+    match table.game.as_ref().and_then(|g| g.turn_key()) {
+        None => table.clock = None,
+        Some(turn) if table.clock.as_ref().is_some_and(|c| c.turn == turn) => {}
+        Some(turn) => {
+            let handle = tokio::spawn(run_clock(Arc::clone(state), table_id.clone(), turn));
+            table.clock = Some(ActionClock { turn, handle });
+        }
+    }
+}
+
+/// Counts down one turn a second at a time. Once the base time is up the player's time
+/// bank is drawn on, and when that is empty too the game acts for them. The whole table
+/// is warned as time runs down. A disconnected player only gets a short grace period,
+/// counted from when they dropped.
+async fn run_clock(state: Arc<Mutex<ServerState>>, table_id: TableId, turn: (usize, usize, usize)) {
+    let mut elapsed = 0;
+    loop {
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        elapsed += 1;
+        {
+            let mut guard = state.lock().unwrap();
+            let state = &mut *guard;
+            let Some(table) = state.tables.get_mut(&table_id) else {
+                return;
+            };
+            let Some(game) = table.game.as_mut().filter(|g| g.turn_key() == Some(turn)) else {
+                return;
+            };
+            let idx = game.current_player;
+            let name = game.players[idx].name.clone();
+            let rules = game.clock;
+            // The grace period runs from the disconnect, not from the start of the turn
+            if let Some(since) = game.players[idx].disconnected_at {
+                if since.elapsed() < Duration::from_secs(rules.disconnect_grace_secs) {
+                    continue;
+                }
+            } else if elapsed <= rules.action_secs {
+                if rules.action_secs - elapsed == WARNING_SECS {
+                    let message = ServerMessage::Event(TableEvent::ClockWarning { player: name, seconds: WARNING_SECS });
+                    send_to_players(&state.writers, &table.audience(), &message);
+                }
+                continue;
+            } else if game.players[idx].time_bank > 0 {
+                game.players[idx].time_bank -= 1;
+                let left = game.players[idx].time_bank;
+                if elapsed == rules.action_secs + 1 {
+                    let message = ServerMessage::Event(TableEvent::TimeBankStarted { player: name, seconds: left });
+                    send_to_players(&state.writers, &table.audience(), &message);
+                } else if left == WARNING_SECS {
+                    let message = ServerMessage::Event(TableEvent::TimeBankWarning { player: name, seconds: left });
+                    send_to_players(&state.writers, &table.audience(), &message);
+                }
+                continue;
+            }
+            let action = game.act_on_timeout(idx);
+            let (_, showdowns) = advance_game(game);
+            let next_player = game.turn_key().and(game.get_current_player()).map(|p| p.name.clone());
+            let message = ServerMessage::Event(TableEvent::TimedOut { player: name, action, showdowns, next_player });
+            send_to_players(&state.writers, &table.audience(), &message);
+        }
+        // Everyone sees the new state, which also starts the clock for whoever acts next
+        broadcast_game_state(&state, &table_id, false).await;
+        ensure_clock(&state, &table_id);
+        return;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+    use crate::game::{ClockRules, Game};
+    use crate::player::{PlayerAction, PlayerState};
+    use crate::variant::{default_variant, Pineapple};
+    use std::collections::HashSet;
+
+    fn clock_task(state: &Arc<Mutex<ServerState>>) -> Option<tokio::task::Id> {
+        state.lock().unwrap().tables["t"].clock.as_ref().map(|c| c.handle.id())
+    }
+
+    /// A server with a single table "t" playing `game`
+    fn serve(game: Game) -> Arc<Mutex<ServerState>> {
+        let table = Table {
+            id: "t".to_string(),
+            host: None,
+            variant: Arc::clone(&game.variant),
+            settings: TableSettings::default(),
+            players: HashSet::new(),
+            spectators: HashSet::new(),
+            game: Some(game),
+            clock: None,
+        };
+        let state = Arc::new(Mutex::new(ServerState::default()));
+        state.lock().unwrap().tables.insert("t".to_string(), table);
+        state
+    }
+
+    #[tokio::test]
+    async fn test_rejected_action_leaves_the_clock_running() {
+        let mut game = Game::new(default_variant(), 1.0, 2.0);
+        for name in ["alice", "bob"] {
+            game.add_player(name.to_string(), 100.0);
+        }
+        game.start_new_hand().unwrap();
+        let state = serve(game);
+        let table_id = "t".to_string();
+        ensure_clock(&state, &table_id);
+        let first = clock_task(&state).unwrap();
+
+        let act = |action: PlayerAction| {
+            let mut guard = state.lock().unwrap();
+            let game = guard.tables.get_mut("t").unwrap().game.as_mut().unwrap();
+            let result = game.player_action(game.current_player, action);
+            if result.is_ok() {
+                advance_game(game);
+            }
+            result
+        };
+        // The small blind cannot check, and trying does not restart their clock
+        assert!(act(PlayerAction::Check).is_err());
+        ensure_clock(&state, &table_id);
+        assert_eq!(clock_task(&state), Some(first));
+
+        act(PlayerAction::Call).unwrap();
+        ensure_clock(&state, &table_id);
+        assert_ne!(clock_task(&state), Some(first));
+    }
+
+    #[tokio::test]
+    async fn test_clock_discards_for_an_all_in_player() {
+        let mut game = Game::new(Arc::new(Pineapple), 1.0, 2.0);
+        game.clock = ClockRules { action_secs: 1, time_bank_secs: 0, refill_secs: 0, ..ClockRules::default() };
+        for name in ["alice", "bob"] {
+            game.add_player(name.to_string(), 100.0);
+        }
+        game.start_new_hand().unwrap();
+        game.start_discard();
+        let idx = game.current_player;
+        game.players[idx].state = PlayerState::AllIn;
+        assert!(game.turn_key().is_some());
+        let state = serve(game);
+        ensure_clock(&state, &"t".to_string());
+
+        tokio::time::sleep(Duration::from_millis(2500)).await;
+        let guard = state.lock().unwrap();
+        let game = guard.tables["t"].game.as_ref().unwrap();
+        assert_eq!(game.players[idx].hole_cards.len(), 2);
+        assert_ne!(game.current_player, idx);
+    }
+
+    #[tokio::test]
+    async fn test_grace_period_counts_from_the_disconnect() {
+        let mut game = Game::new(default_variant(), 1.0, 2.0);
+        game.clock = ClockRules { action_secs: 10, disconnect_grace_secs: 1, ..ClockRules::default() };
+        for name in ["alice", "bob"] {
+            game.add_player(name.to_string(), 100.0);
+        }
+        game.start_new_hand().unwrap();
+        let first = game.current_player;
+        let turn = game.turn_key();
+        let state = serve(game);
+        ensure_clock(&state, &"t".to_string());
+        let current = || state.lock().unwrap().tables["t"].game.as_ref().unwrap().turn_key();
+
+        // Dropping well into the turn still leaves the whole grace period to come back
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        let name = state.lock().unwrap().tables["t"].game.as_ref().unwrap().players[first].name.clone();
+        state.lock().unwrap().tables.get_mut("t").unwrap().game.as_mut().unwrap().set_disconnected(&name, true);
+        tokio::time::sleep(Duration::from_millis(900)).await;
+        assert_eq!(current(), turn);
+        tokio::time::sleep(Duration::from_millis(1200)).await;
+        assert_ne!(current(), turn);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_player_is_acted_for_and_can_reconnect() {
+        let url = start_gateway().await;
+        let mut alice = json_client(&url, "alice").await;
+        alice.send(Message::text(r#"{"type":"create_table","table":"t","options":{"disconnect_grace":"1"}}"#)).await.unwrap();
+        alice.send(Message::text(r#"{"type":"join_table","table":"t"}"#)).await.unwrap();
+        wait_for(&mut alice, "joined").await;
+        let (mut bob, _) = connect_async(&url).await.unwrap();
+        bob.send(Message::text("PROTOCOL json")).await.unwrap();
+        bob.send(Message::text(r#"{"type":"register","name":"bob","password":"hunter22"}"#)).await.unwrap();
+        let registered: serde_json::Value = serde_json::from_str(&wait_for(&mut bob, "registered").await).unwrap();
+        let token = registered["token"].as_str().unwrap().to_string();
+        bob.send(Message::text(r#"{"type":"join_table","table":"t"}"#)).await.unwrap();
+        wait_for(&mut bob, "joined").await;
+
+        drop(bob);
+        wait_for(&mut alice, r#""event":"disconnected","player":"bob""#).await;
+        let state: serde_json::Value = serde_json::from_str(&wait_for(&mut alice, r#""type":"state""#).await).unwrap();
+        assert!(state["seats"].as_array().unwrap().iter().any(|s| s["name"] == "bob" && s["disconnected"] == true));
+        if state["current_player"] == "alice" {
+            alice.send(Message::text(r#"{"type":"call"}"#)).await.unwrap();
+        }
+        // Bob's turn is played for him once the grace period is up, without his time bank
+        wait_for(&mut alice, r#""event":"timed_out","player":"bob""#).await;
+
+        let mut bob = json_client(&url, "bob2").await;
+        bob.send(Message::text(format!(r#"{{"type":"resume","token":"{}"}}"#, token))).await.unwrap();
+        wait_for(&mut bob, "resumed").await;
+        wait_for(&mut alice, r#""event":"reconnected","player":"bob""#).await;
+    }
+}
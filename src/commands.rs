@@ -1,351 +1,945 @@
 use crate::models::*;
+use crate::account::{dummy_hash, hash_password, verify_password, Account};
+use crate::chat::prepare_message;
+use crate::session::issue_token;
+use crate::protocol::{ChatScope, Payout, PotWon, RevealedHand, ServerMessage, Showdown, TableEvent, TableSummary};
+use crate::view::PlayerView;
+use crate::card::Card;
+use crate::game::{BettingRound, Game, PotResult};
+use crate::history::ActionTaken;
+use crate::player::PlayerAction;
+use crate::variant::GameVariant;
 use tokio::sync::mpsc::UnboundedSender;
 use std::sync::{Arc, Mutex};
-use crate::utils::send_game_state;
+use crate::utils::{broadcast_game_state, send_game_state, send_to_players};
+use std::collections::HashSet;
+use std::time::Instant;
 
-/// Handles user registration. Registers a new user if the name is not taken.
-pub fn handle_register(name: &str, state: &Arc<Mutex<ServerState>>, tx: &UnboundedSender<String>, user_id: &mut Option<UserId>) {
-    let mut already_exists = false;
+/// Handles user registration. Registers a new user with a password if the name is not taken.
+pub async fn handle_register(name: &str, password: &str, state: &Arc<Mutex<ServerState>>, tx: &UnboundedSender<ServerMessage>, user_id: &mut Option<UserId>) {
+    if user_id.is_some() {
+        let _ = tx.send(ServerMessage::error("You are already logged in"));
+        return;
+    }
+    if state.lock().unwrap().users.contains_key(name) {
+        let _ = tx.send(ServerMessage::error("Username already taken"));
+        return;
+    }
+    let password_hash = match hash_in_background(password).await {
+        Ok(hash) => hash,
+        Err(e) => {
+            let _ = tx.send(ServerMessage::error(e));
+            return;
+        }
+    };
+    let mut token = None;
+    let mut balance = 0.0;
     {
         let mut state = state.lock().unwrap();
-        if state.users.contains_key(name) {
-            already_exists = true;
-        } else {
-            state.users.insert(name.to_string(), User { name: name.to_string(), balance: 100.0, table: None });
+        // Someone may have taken the name while the password was hashed
+        if !state.users.contains_key(name) {
+            balance = state.config.game.starting_balance;
+            state.users.insert(name.to_string(), User { name: name.to_string(), balance, account: Account::new(password_hash), table: None, watching: None, muted: HashSet::new() });
             *user_id = Some(name.to_string());
             state.writers.insert(name.to_string(), tx.clone());
+            token = Some(issue_token(&mut state.sessions, name));
         }
     }
-    if already_exists {
-        let _ = tx.send("Username already taken\n".to_string());
-    } else {
-        let _ = tx.send("Registered successfully. Your balance: 100\n".to_string());
+    match token {
+        Some(token) => {
+            let _ = tx.send(ServerMessage::Registered { name: name.to_string(), balance, token });
+        }
+        None => {
+            let _ = tx.send(ServerMessage::error("Username already taken"));
+        }
     }
 }
 
-/// Handles table creation. Creates a new table if the name is not taken.
-pub fn handle_create_table(table: &str, state: &Arc<Mutex<ServerState>>, tx: &UnboundedSender<String>) {
-    let mut already_exists = false;
+/// Handles a returning user's login. Checks their password, locking the account after
+/// repeated failures, then attaches this connection to them as RESUME does. An unknown
+/// name is checked against a dummy hash, so it takes as long to refuse as a wrong password.
+pub async fn handle_login(name: &str, password: &str, state: &Arc<Mutex<ServerState>>, tx: &UnboundedSender<ServerMessage>, user_id: &mut Option<UserId>) {
+    if user_id.is_some() {
+        let _ = tx.send(ServerMessage::error("You are already logged in"));
+        return;
+    }
+    let password_hash = {
+        let state = state.lock().unwrap();
+        match state.users.get(name) {
+            Some(user) => user.account.check_unlocked(Instant::now()).map(|_| user.account.password_hash().to_string()),
+            None => Ok(dummy_hash().to_string()),
+        }
+    };
+    let password_hash = match password_hash {
+        Ok(hash) => hash,
+        Err(e) => {
+            let _ = tx.send(ServerMessage::error(e));
+            return;
+        }
+    };
+    let passed = verify_in_background(password_hash, password).await;
+    let logged_in = {
+        let mut state = state.lock().unwrap();
+        let attempt = match state.users.get_mut(name) {
+            Some(user) => user.account.record_attempt(passed, Instant::now()),
+            None => Err("Wrong name or password".to_string()),
+        };
+        attempt.map(|_| {
+            let token = issue_token(&mut state.sessions, name);
+            (token, take_over(&mut state, name, tx))
+        })
+    };
+    let (token, (balance, table)) = match logged_in {
+        Ok(logged_in) => logged_in,
+        Err(e) => {
+            let _ = tx.send(ServerMessage::error(e));
+            return;
+        }
+    };
+    *user_id = Some(name.to_string());
+    let _ = tx.send(ServerMessage::LoggedIn { name: name.to_string(), balance, token, table: table.clone() });
+    // Everyone at the table sees the player is back, and the player gets a full resync
+    if let Some(table) = &table {
+        broadcast_game_state(state, table, false).await;
+    }
+}
+
+/// Handles a password change. The old password must be given, and wrong guesses count
+/// towards the lockout just as failed logins do. Every session token the user held is
+/// revoked and this connection is given a new one.
+pub async fn handle_change_password(user_id: &Option<UserId>, old: &str, new: &str, state: &Arc<Mutex<ServerState>>, tx: &UnboundedSender<ServerMessage>) {
+    let Some(uid) = user_id else {
+        let _ = tx.send(ServerMessage::error("You must register first"));
+        return;
+    };
+    let password_hash = {
+        let state = state.lock().unwrap();
+        let account = &state.users[uid].account;
+        account.check_unlocked(Instant::now()).map(|_| account.password_hash().to_string())
+    };
+    let changed = match password_hash {
+        Ok(hash) => {
+            let passed = verify_in_background(hash, old).await;
+            let attempt = state.lock().unwrap().users.get_mut(uid).unwrap().account.record_attempt(passed, Instant::now());
+            match attempt {
+                Ok(()) => hash_in_background(new).await,
+                Err(e) => Err(e),
+            }
+        }
+        Err(e) => Err(e),
+    };
+    match changed {
+        Ok(hash) => {
+            let token = {
+                let mut state = state.lock().unwrap();
+                state.users.get_mut(uid).unwrap().account.set_password_hash(hash);
+                issue_token(&mut state.sessions, uid)
+            };
+            let _ = tx.send(ServerMessage::PasswordChanged { token });
+        }
+        Err(e) => {
+            let _ = tx.send(ServerMessage::error(e));
+        }
+    }
+}
+
+/// Hashes a new password off the async runtime, since the KDF is deliberately slow
+async fn hash_in_background(password: &str) -> Result<String, String> {
+    let password = password.to_string();
+    tokio::task::spawn_blocking(move || hash_password(&password))
+        .await
+        .unwrap_or_else(|e| Err(format!("Could not hash password: {}", e)))
+}
+
+/// Checks a password against its hash off the async runtime
+async fn verify_in_background(password_hash: String, password: &str) -> bool {
+    let password = password.to_string();
+    tokio::task::spawn_blocking(move || verify_password(&password_hash, &password))
+        .await
+        .unwrap_or(false)
+}
+
+/// Points the user's messages at this connection, so any old connection loses the
+/// session on its next command, and returns their balance and the table they are at
+/// or watching
+fn take_over(state: &mut ServerState, name: &str, tx: &UnboundedSender<ServerMessage>) -> (f64, Option<TableId>) {
+    state.writers.insert(name.to_string(), tx.clone());
+    let user = &state.users[name];
+    let (balance, seat, watching) = (user.balance, user.table.clone(), user.watching.clone());
+    // A player who had dropped is dealt in again, starting a hand if the table was waiting on them
+    if let Some(table) = seat.as_ref().and_then(|t| state.tables.get_mut(t))
+        && let Some(game) = table.game.as_mut()
+        && game.players.iter().any(|p| p.name == name && p.disconnected_at.is_some())
     {
+        game.set_disconnected(name, false);
+        let showdowns = if game.hand_in_progress { Vec::new() } else { deal_next_hand(game) };
+        let message = ServerMessage::Event(TableEvent::Reconnected { player: name.to_string() });
+        send_to_players(&state.writers, &table.audience(), &message);
+        for showdown in showdowns {
+            send_to_players(&state.writers, &table.audience(), &ServerMessage::Event(TableEvent::Showdown(showdown)));
+        }
+    }
+    (balance, seat.or(watching))
+}
+
+/// Cleans up after a connection closes: its writer is dropped, a spectator stops watching
+/// and a seated user is marked disconnected so the game acts for them after a grace period.
+/// A connection that lost its session to RESUME or LOGIN leaves the user alone.
+pub async fn handle_disconnect(user_id: &Option<UserId>, state: &Arc<Mutex<ServerState>>, tx: &UnboundedSender<ServerMessage>) {
+    let Some(uid) = user_id else {
+        return;
+    };
+    let table_id = {
+        let mut guard = state.lock().unwrap();
+        let state = &mut *guard;
+        if !state.writers.get(uid).is_some_and(|writer| writer.same_channel(tx)) {
+            return;
+        }
+        state.writers.remove(uid);
+        // A spectator gives up their place; they can WATCH_TABLE again once back
+        if let Some(watched) = state.users.get_mut(uid).and_then(|u| u.watching.take())
+            && let Some(table) = state.tables.get_mut(&watched)
+        {
+            table.spectators.remove(uid);
+        }
+        let Some(table_id) = state.users.get(uid).and_then(|u| u.table.clone()) else {
+            return;
+        };
+        let Some(table) = state.tables.get_mut(&table_id) else {
+            return;
+        };
+        if let Some(game) = table.game.as_mut() {
+            game.set_disconnected(uid, true);
+        }
+        let message = ServerMessage::Event(TableEvent::Disconnected { player: uid.clone() });
+        send_to_players(&state.writers, &table.audience(), &message);
+        table_id
+    };
+    broadcast_game_state(state, &table_id, false).await;
+}
+
+/// Reattaches this connection to the user a session token was issued to, taking over
+/// from their old connection, and sends them everything they need to carry on.
+pub async fn handle_resume(token: &str, state: &Arc<Mutex<ServerState>>, tx: &UnboundedSender<ServerMessage>, user_id: &mut Option<UserId>) {
+    let resumed = {
+        let mut state = state.lock().unwrap();
+        state.sessions.get(token).cloned().map(|name| {
+            let (balance, table) = take_over(&mut state, &name, tx);
+            (name, balance, table)
+        })
+    };
+    let Some((name, balance, table)) = resumed else {
+        let _ = tx.send(ServerMessage::error("Unknown or expired session token"));
+        return;
+    };
+    *user_id = Some(name.clone());
+    let _ = tx.send(ServerMessage::Resumed { name, balance, table: table.clone() });
+    // Everyone at the table sees the player is back, and the player gets a full resync
+    if let Some(table) = &table {
+        broadcast_game_state(state, table, false).await;
+    }
+}
+
+/// Handles table creation. Creates a new table if the name is not taken.
+pub fn handle_create_table(user_id: &Option<UserId>, table: &str, variant: Arc<dyn GameVariant>, settings: TableSettings, state: &Arc<Mutex<ServerState>>, tx: &UnboundedSender<ServerMessage>) {
+    let name = variant.name().to_string();
+    let error = {
         let mut state = state.lock().unwrap();
         if state.tables.contains_key(table) {
-            already_exists = true;
+            Some("Table already exists".to_string())
+        } else if state.tables.len() >= state.config.server.max_tables {
+            Some(format!("The server already has the most tables it allows ({})", state.config.server.max_tables))
         } else {
-            state.tables.insert(table.to_string(), Table { id: table.to_string(), players: std::collections::HashSet::new(), game: None });
+            state.tables.insert(table.to_string(), Table { id: table.to_string(), host: user_id.clone(), variant, settings, players: std::collections::HashSet::new(), spectators: std::collections::HashSet::new(), game: None, clock: None });
+            None
+        }
+    };
+    match error {
+        Some(error) => {
+            let _ = tx.send(ServerMessage::error(error));
+        }
+        None => {
+            let _ = tx.send(ServerMessage::TableCreated { table: table.to_string(), variant: name });
         }
-    }
-    if already_exists {
-        let _ = tx.send("Table already exists\n".to_string());
-    } else {
-        let _ = tx.send("Table created\n".to_string());
     }
 }
 
 /// Handles joining a table. Adds the user to the table if it exists.
-pub fn handle_join_table(user_id: &Option<UserId>, table: &str, state: &Arc<Mutex<ServerState>>, tx: &UnboundedSender<String>) {
+pub async fn handle_join_table(user_id: &Option<UserId>, table: &str, state: &Arc<Mutex<ServerState>>, tx: &UnboundedSender<ServerMessage>) {
     if let Some(uid) = user_id {
         let table_key = table.to_string();
         let user_key = uid.clone().to_string();
         let mut joined = false;
         {
             let mut state = state.lock().unwrap();
+            let mut showdowns = Vec::new();
+            let balance = state.users.get(&user_key).map(|u| u.balance).unwrap_or(0.0);
+            let stakes = state.config.game.clone();
             if let Some(table_obj) = state.tables.get_mut(&table_key) {
-                table_obj.players.insert(user_key.clone());
+                if let Some(max) = table_obj.variant.max_players()
+                    && table_obj.players.len() >= max
+                    && !table_obj.players.contains(&user_key)
+                {
+                    let _ = tx.send(ServerMessage::error(format!("Table is full ({} seats)", max)));
+                    return;
+                }
+                if table_obj.players.insert(user_key.clone()) {
+                    let game = table_obj.game.get_or_insert_with(|| {
+                        let mut game = Game::new(Arc::clone(&table_obj.variant), stakes.small_blind, stakes.big_blind);
+                        if table_obj.variant.is_stud() {
+                            game.ante = stakes.ante;
+                        }
+                        game.bomb_pots = table_obj.settings.bomb_pots;
+                        game.all_in_face_up = table_obj.settings.all_in_face_up;
+                        game.clock = table_obj.settings.clock;
+                        game
+                    });
+                    game.add_player(user_key.clone(), balance);
+                    if !game.hand_in_progress {
+                        showdowns = deal_next_hand(game);
+                    }
+                }
                 joined = true;
             }
-            if let Some(user) = state.users.get_mut(&user_key) {
+            if joined && let Some(user) = state.users.get_mut(&user_key) {
                 user.table = Some(table_key.clone());
+                // Taking a seat ends watching
+                if let Some(watched) = user.watching.take()
+                    && let Some(table_obj) = state.tables.get_mut(&watched)
+                {
+                    table_obj.spectators.remove(&user_key);
+                }
+            }
+            if let Some(table_obj) = state.tables.get(&table_key) {
+                for showdown in showdowns {
+                    send_to_players(&state.writers, &table_obj.audience(), &ServerMessage::Event(TableEvent::Showdown(showdown)));
+                }
             }
         }
         if joined {
-            let _ = tx.send("Joined table\n".to_string());
+            let _ = tx.send(ServerMessage::Joined { table: table_key.clone() });
+            // A join can start a hand, so everyone seated learns whose turn it is
+            broadcast_game_state(state, &table_key, false).await;
         } else {
-            let _ = tx.send("Table not found\n".to_string());
+            let _ = tx.send(ServerMessage::error("Table not found"));
         }
     } else {
-        let _ = tx.send("You must register first\n".to_string());
+        let _ = tx.send(ServerMessage::error("You must register first"));
+    }
+}
+
+/// Lets the user watch a table without a seat, up to the table's spectator limit.
+/// Spectators follow the table's events and see only what is public.
+pub async fn handle_watch_table(user_id: &Option<UserId>, table: &str, state: &Arc<Mutex<ServerState>>, tx: &UnboundedSender<ServerMessage>) {
+    let Some(uid) = user_id else {
+        let _ = tx.send(ServerMessage::error("You must register first"));
+        return;
+    };
+    let watched = {
+        let mut guard = state.lock().unwrap();
+        let state = &mut *guard;
+        let Some(user) = state.users.get_mut(uid) else {
+            return;
+        };
+        match state.tables.get_mut(table) {
+            None => Err("Table not found"),
+            Some(_) if user.table.is_some() => Err("You are already seated at a table"),
+            // Watching again only resends the state, so it does not count against the limit
+            Some(table_obj) if table_obj.spectators.contains(uid) => Ok(()),
+            Some(table_obj) if table_obj.spectators.len() >= table_obj.settings.max_spectators => {
+                Err("This table has reached its spectator limit")
+            }
+            Some(_) => {
+                if let Some(previous) = user.watching.replace(table.to_string())
+                    && previous != table
+                    && let Some(previous) = state.tables.get_mut(&previous)
+                {
+                    previous.spectators.remove(uid);
+                }
+                if let Some(table_obj) = state.tables.get_mut(table) {
+                    table_obj.spectators.insert(uid.clone());
+                }
+                Ok(())
+            }
+        }
+    };
+    match watched {
+        Ok(()) => {
+            let _ = tx.send(ServerMessage::Watching { table: table.to_string() });
+            send_game_state(state, user_id, tx, false).await;
+        }
+        Err(e) => {
+            let _ = tx.send(ServerMessage::error(e));
+        }
+    }
+}
+
+/// Stops the user watching the table they are watching.
+pub fn handle_unwatch(user_id: &Option<UserId>, state: &Arc<Mutex<ServerState>>, tx: &UnboundedSender<ServerMessage>) {
+    let Some(uid) = user_id else {
+        let _ = tx.send(ServerMessage::error("You must register first"));
+        return;
+    };
+    let mut guard = state.lock().unwrap();
+    let state = &mut *guard;
+    match state.users.get_mut(uid).and_then(|u| u.watching.take()) {
+        Some(table) => {
+            if let Some(table) = state.tables.get_mut(&table) {
+                table.spectators.remove(uid);
+            }
+            let _ = tx.send(ServerMessage::ok(format!("Stopped watching {}", table)));
+        }
+        None => {
+            let _ = tx.send(ServerMessage::error("You are not watching a table"));
+        }
     }
 }
 
 /// Lists all available tables.
-pub fn handle_list_tables(state: &Arc<Mutex<ServerState>>, tx: &UnboundedSender<String>) {
-    let list = {
+pub fn handle_list_tables(state: &Arc<Mutex<ServerState>>, tx: &UnboundedSender<ServerMessage>) {
+    let tables = {
         let state = state.lock().unwrap();
-        state.tables.keys().cloned().collect::<Vec<_>>().join(", ")
+        state.tables.keys().cloned().collect::<Vec<_>>()
     };
-    let _ = tx.send(format!("Tables: {}\n", list));
+    let _ = tx.send(ServerMessage::Tables { tables });
 }
 
 /// Shows the current user's info and table state.
-pub async fn handle_show(user_id: &Option<UserId>, state: &Arc<Mutex<ServerState>>, tx: &UnboundedSender<String>) {
-    let (user_name, user_balance, table_info) = {
+pub async fn handle_show(user_id: &Option<UserId>, state: &Arc<Mutex<ServerState>>, tx: &UnboundedSender<ServerMessage>) {
+    let profile = {
         let state = state.lock().unwrap();
-        if let Some(uid) = user_id {
-            if let Some(user) = state.users.get(uid) {
-                let user_name = user.name.clone();
-                let user_balance = user.balance;
-                let mut table_info = String::new();
-                if let Some(ref table_id) = user.table {
-                    if let Some(table) = state.tables.get(table_id) {
-                        table_info.push_str(&format!("Table: {}\nPlayers: {:?}\n", table.id, table.players));
-                        if let Some(ref game) = table.game {
-                            table_info.push_str(&format!("Pot: {}\nCommunity cards: {:?}\n", game.get_pot(), game.get_community_cards()));
-                            for (i, player) in game.players.iter().enumerate() {
-                                table_info.push_str(&format!("Player {}: {} | Cards: {:?} | Balance: {}\n", i, player.name, player.hole_cards, player.balance));
-                            }
-                        }
+        user_id.as_ref().and_then(|uid| state.users.get(uid)).map(|user| {
+            let table = user.table.as_ref()
+                .or(user.watching.as_ref())
+                .and_then(|table_id| state.tables.get(table_id))
+                .map(|table| {
+                    let mut players: Vec<String> = table.players.iter().cloned().collect();
+                    players.sort();
+                    TableSummary {
+                        table: table.id.clone(),
+                        players,
+                        game: table.game.as_ref().map(|g| PlayerView::new(g, &user.name)),
                     }
-                }
-                (user_name, user_balance, table_info)
-            } else {
-                (String::new(), 0.0, String::new())
-            }
-        } else {
-            (String::new(), 0.0, String::new())
-        }
+                });
+            ServerMessage::Profile { name: user.name.clone(), balance: user.balance, table }
+        })
     };
-    if !user_name.is_empty() {
-        let _ = tx.send(format!("You: {} | Balance: {}\n", user_name, user_balance));
-        if !table_info.is_empty() {
-            let _ = tx.send(table_info);
-        }
+    if let Some(profile) = profile {
+        let _ = tx.send(profile);
     }
 }
 
 /// Handles the quit command. Sends a goodbye message.
-pub async fn handle_quit(tx: &UnboundedSender<String>) {
-    let _ = tx.send("Bye!\n".to_string());
+pub async fn handle_quit(tx: &UnboundedSender<ServerMessage>) {
+    let _ = tx.send(ServerMessage::Bye);
 }
 
 /// Handles a bet action from the user.
-pub async fn handle_bet(user_id: &Option<UserId>, amount: f64, state: &Arc<Mutex<ServerState>>, tx: &UnboundedSender<String>) {
-    let mut result = String::new();
-    let mut next_player_name = None;
-    let mut round_ended = false;
-    let mut winner_info = None;
-    {
-        let mut state = state.lock().unwrap();
-        if let Some(uid) = user_id {
-            for table in state.tables.values_mut() {
-                if let Some(game) = &mut table.game {
-                    if let Some(idx) = game.players.iter().position(|p| &p.name == uid) {
-                        match game.player_action(idx, crate::player::PlayerAction::Raise(amount)) {
-                            Ok(_) => {
-                                result = format!("You bet {}\n", amount);
-                                if game.is_betting_round_complete() {
-                                    round_ended = true;
-                                    match game.current_round {
-                                        crate::game::BettingRound::PreFlop => game.deal_flop(),
-                                        crate::game::BettingRound::Flop => game.deal_turn(),
-                                        crate::game::BettingRound::Turn => game.deal_river(),
-                                        crate::game::BettingRound::River => {
-                                            // Showdown
-                                            let hands = crate::hand::evaluate_hand;
-                                            let mut best: Option<(String, crate::hand::EvaluatedHand)> = None;
-                                            let mut winner = None;
-                                            for player in &game.players {
-                                                let mut all_cards = player.hole_cards.clone();
-                                                all_cards.extend(game.community_cards.iter().cloned());
-                                                let hand = hands(&all_cards);
-                                                if best.is_none() || hand > best.as_ref().unwrap().1 {
-                                                    best = Some((player.name.clone(), hand.clone()));
-                                                    winner = Some(player.name.clone());
-                                                }
-                                            }
-                                            if let Some(winner) = winner {
-                                                winner_info = Some(winner);
-                                            }
-                                        }
-                                        _ => {}
-                                    }
-                                } else {
-                                    game.next_player();
-                                }
-                                next_player_name = game.get_current_player().map(|p| p.name.clone());
-                            }
-                            Err(e) => result = format!("Bet error: {}\n", e),
-                        }
-                    }
-                }
-            }
+pub async fn handle_bet(user_id: &Option<UserId>, amount: f64, state: &Arc<Mutex<ServerState>>, tx: &UnboundedSender<ServerMessage>) {
+    play(user_id, state, tx, "Bet", true, |game, idx| {
+        let taken = game.player_action(idx, PlayerAction::Raise(amount))?;
+        Ok((reply_to(&taken, || format!("You bet {}", amount)), taken))
+    }).await;
+}
+
+/// Handles a call action from the user.
+pub async fn handle_call(user_id: &Option<UserId>, state: &Arc<Mutex<ServerState>>, tx: &UnboundedSender<ServerMessage>) {
+    play(user_id, state, tx, "Call", true, |game, idx| {
+        let taken = game.player_action(idx, PlayerAction::Call)?;
+        Ok((reply_to(&taken, || "You called".to_string()), taken))
+    }).await;
+}
+
+/// Handles the user pushing their whole stack in.
+pub async fn handle_all_in(user_id: &Option<UserId>, state: &Arc<Mutex<ServerState>>, tx: &UnboundedSender<ServerMessage>) {
+    play(user_id, state, tx, "All-in", true, |game, idx| {
+        let taken = game.player_action(idx, PlayerAction::AllIn)?;
+        Ok((reply_to(&taken, || "You are all-in".to_string()), taken))
+    }).await;
+}
+
+/// What the player is told after a bet or call, which may have turned into a check or an all-in
+fn reply_to(taken: &ActionTaken, otherwise: impl FnOnce() -> String) -> ServerMessage {
+    match taken {
+        ActionTaken::AllIn { amount } => ServerMessage::ok(format!("You are all-in for {}", amount)),
+        ActionTaken::Check => ServerMessage::ok("You checked"),
+        _ => ServerMessage::ok(otherwise()),
+    }
+}
+
+/// Handles a check action from the user.
+pub async fn handle_check(user_id: &Option<UserId>, state: &Arc<Mutex<ServerState>>, tx: &UnboundedSender<ServerMessage>) {
+    play(user_id, state, tx, "Check", true, |game, idx| {
+        let taken = game.player_action(idx, PlayerAction::Check)?;
+        Ok((ServerMessage::ok("You checked"), taken))
+    }).await;
+}
+
+/// Handles a fold action from the user.
+pub async fn handle_fold(user_id: &Option<UserId>, state: &Arc<Mutex<ServerState>>, tx: &UnboundedSender<ServerMessage>) {
+    play(user_id, state, tx, "Fold", true, |game, idx| {
+        let taken = game.player_action(idx, PlayerAction::Fold)?;
+        Ok((ServerMessage::ok("You folded"), taken))
+    }).await;
+}
+
+/// Handles a draw: discards the given cards and deals replacements. No cards means standing pat.
+pub async fn handle_draw(user_id: &Option<UserId>, discards: Vec<Card>, state: &Arc<Mutex<ServerState>>, tx: &UnboundedSender<ServerMessage>) {
+    play(user_id, state, tx, "Draw", true, |game, idx| {
+        let before = game.players[idx].hole_cards.clone();
+        let taken = game.player_action(idx, PlayerAction::Draw(discards))?;
+        if taken == (ActionTaken::Draw { count: 0 }) {
+            return Ok((ServerMessage::ok("You stand pat"), taken));
+        }
+        let new_cards: Vec<String> = game.players[idx].hole_cards.iter()
+            .filter(|c| !before.contains(c))
+            .map(|c| c.to_string())
+            .collect();
+        Ok((ServerMessage::ok(format!("You drew {}", new_cards.join(" "))), taken))
+    }).await;
+}
+
+/// Handles a Pineapple discard of one hole card.
+pub async fn handle_discard(user_id: &Option<UserId>, card: Card, state: &Arc<Mutex<ServerState>>, tx: &UnboundedSender<ServerMessage>) {
+    play(user_id, state, tx, "Discard", true, |game, idx| {
+        let taken = game.player_action(idx, PlayerAction::Discard(card))?;
+        Ok((ServerMessage::ok(format!("You discarded {}", card)), taken))
+    }).await;
+}
+
+/// Handles a vote to run the rest of the board `runs` times once everyone is all-in.
+/// Votes are taken in any order.
+pub async fn handle_run(user_id: &Option<UserId>, runs: usize, state: &Arc<Mutex<ServerState>>, tx: &UnboundedSender<ServerMessage>) {
+    play(user_id, state, tx, "Run", false, |game, idx| {
+        game.vote_runs(idx, runs)?;
+        Ok((ServerMessage::ok(format!("You voted to run it {} time(s)", runs)), ActionTaken::Vote { runs }))
+    }).await;
+}
+
+/// Handles the user's choice at showdown to turn over or muck a losing hand.
+pub async fn handle_show_or_muck(user_id: &Option<UserId>, show: bool, state: &Arc<Mutex<ServerState>>, tx: &UnboundedSender<ServerMessage>) {
+    let label = if show { "Show" } else { "Muck" };
+    play(user_id, state, tx, label, true, |game, idx| {
+        let taken = game.show_or_muck(idx, show)?;
+        let reply = match &taken {
+            ActionTaken::Show { .. } => ServerMessage::ok("You showed your hand"),
+            _ => ServerMessage::ok("You mucked your hand"),
+        };
+        Ok((reply, taken))
+    }).await;
+}
+
+/// Makes a move for the user in the game at their table, then advances the game.
+/// When `in_turn` is set the move is refused unless it is the user's turn. `make`
+/// returns the reply to the user and what the rest of the table is told; failures
+/// are reported as `<label> error: ...`.
+async fn play(
+    user_id: &Option<UserId>,
+    state: &Arc<Mutex<ServerState>>,
+    tx: &UnboundedSender<ServerMessage>,
+    label: &str,
+    in_turn: bool,
+    make: impl FnOnce(&mut Game, usize) -> Result<(ServerMessage, ActionTaken), String>,
+) {
+    let Some(uid) = user_id else {
+        let _ = tx.send(ServerMessage::error("You must register first"));
+        return;
+    };
+    let outcome = {
+        let mut guard = state.lock().unwrap();
+        let state = &mut *guard;
+        let seat = state.users.get(uid)
+            .and_then(|u| u.table.as_ref())
+            .and_then(|t| state.tables.get_mut(t))
+            .and_then(|t| t.game.as_mut())
+            .and_then(|game| game.players.iter().position(|p| &p.name == uid).map(|idx| (game, idx)));
+        match seat {
+            None => Err("You are not playing at a table".to_string()),
+            Some((game, idx)) if in_turn && idx != game.current_player => Err("It's not your turn".to_string()),
+            Some((game, idx)) => make(game, idx).map(|(reply, acted)| {
+                let (round_ended, showdowns) = advance_game(game);
+                (reply, acted, round_ended, showdowns)
+            }),
+        }
+    };
+    match outcome {
+        Ok((reply, acted, round_ended, showdowns)) => {
+            let _ = tx.send(reply);
+            finish_action(state, uid, acted, showdowns, round_ended).await;
+        }
+        Err(e) => {
+            let _ = tx.send(ServerMessage::error(format!("{} error: {}", label, e)));
+            // Nothing changed, so only the user needs the game state again
+            send_game_state(state, user_id, tx, false).await;
         }
     }
-    let _ = tx.send(result.clone());
-    send_game_state(state, user_id, tx, next_player_name, round_ended, winner_info).await;
 }
 
-/// Handles a call action from the user.
-pub async fn handle_call(user_id: &Option<UserId>, state: &Arc<Mutex<ServerState>>, tx: &UnboundedSender<String>) {
-    let mut result = String::new();
-    let mut next_player_name = None;
-    let mut round_ended = false;
-    let mut winner_info = None;
-    {
-        let mut state = state.lock().unwrap();
-        if let Some(uid) = user_id {
-            for table in state.tables.values_mut() {
-                if let Some(game) = &mut table.game {
-                    if let Some(idx) = game.players.iter().position(|p| &p.name == uid) {
-                        match game.player_action(idx, crate::player::PlayerAction::Call) {
-                            Ok(_) => {
-                                result = "You called\n".to_string();
-                                if game.is_betting_round_complete() {
-                                    round_ended = true;
-                                    match game.current_round {
-                                        crate::game::BettingRound::PreFlop => game.deal_flop(),
-                                        crate::game::BettingRound::Flop => game.deal_turn(),
-                                        crate::game::BettingRound::Turn => game.deal_river(),
-                                        crate::game::BettingRound::River => {
-                                            // Showdown
-                                            let hands = crate::hand::evaluate_hand;
-                                            let mut best: Option<(String, crate::hand::EvaluatedHand)> = None;
-                                            let mut winner = None;
-                                            for player in &game.players {
-                                                let mut all_cards = player.hole_cards.clone();
-                                                all_cards.extend(game.community_cards.iter().cloned());
-                                                let hand = hands(&all_cards);
-                                                if best.is_none() || hand > best.as_ref().unwrap().1 {
-                                                    best = Some((player.name.clone(), hand.clone()));
-                                                    winner = Some(player.name.clone());
-                                                }
-                                            }
-                                            if let Some(winner) = winner {
-                                                winner_info = Some(winner);
-                                            }
-                                        }
-                                        _ => {}
-                                    }
-                                } else {
-                                    game.next_player();
-                                }
-                                next_player_name = game.get_current_player().map(|p| p.name.clone());
-                            }
-                            Err(e) => result = format!("Call error: {}\n", e),
-                        }
-                    }
-                }
-            }
+/// Wraps up an accepted action: tells the rest of the user's table what they did and
+/// any showdown it led to, then sends everyone at the table their view of the game.
+async fn finish_action(state: &Arc<Mutex<ServerState>>, uid: &UserId, action: ActionTaken, showdowns: Vec<Showdown>, round_ended: bool) {
+    let table_id = {
+        let state = state.lock().unwrap();
+        let Some(table) = state.users.get(uid)
+            .and_then(|u| u.table.as_ref())
+            .and_then(|t| state.tables.get(t))
+        else {
+            return;
+        };
+        let others: HashSet<UserId> = table.audience().into_iter().filter(|p| p != uid).collect();
+        send_to_players(&state.writers, &others, &ServerMessage::Event(TableEvent::Acted { player: uid.clone(), action }));
+        for showdown in showdowns {
+            send_to_players(&state.writers, &table.audience(), &ServerMessage::Event(TableEvent::Showdown(showdown)));
+        }
+        table.id.clone()
+    };
+    broadcast_game_state(state, &table_id, round_ended).await;
+}
+
+/// Shows some or all of the user's hole cards from the hand that just ended to everyone at the table.
+pub fn handle_show_cards(user_id: &Option<UserId>, cards: Vec<Card>, state: &Arc<Mutex<ServerState>>, tx: &UnboundedSender<ServerMessage>) {
+    let Some(uid) = user_id else {
+        let _ = tx.send(ServerMessage::error("You must register first"));
+        return;
+    };
+    let mut guard = state.lock().unwrap();
+    let state = &mut *guard;
+    let Some(table) = state.users.get(uid)
+        .and_then(|u| u.table.as_ref())
+        .and_then(|t| state.tables.get_mut(t))
+    else {
+        let _ = tx.send(ServerMessage::error("You are not at a table"));
+        return;
+    };
+    let shown = match &mut table.game {
+        Some(game) => game.show_cards(uid, &cards, std::time::Instant::now()),
+        None => Err("No hand has finished yet".to_string()),
+    };
+    match shown {
+        Ok(cards) => {
+            let message = ServerMessage::Event(TableEvent::ShowsCards { player: uid.clone(), cards });
+            send_to_players(&state.writers, &table.audience(), &message);
+        }
+        Err(e) => {
+            let _ = tx.send(ServerMessage::error(format!("Show error: {}", e)));
         }
     }
-    let _ = tx.send(result.clone());
-    send_game_state(state, user_id, tx, next_player_name, round_ended, winner_info).await;
 }
 
-/// Handles a check action from the user.
-pub async fn handle_check(user_id: &Option<UserId>, state: &Arc<Mutex<ServerState>>, tx: &UnboundedSender<String>) {
-    let mut result = String::new();
-    let mut next_player_name = None;
-    let mut round_ended = false;
-    let mut winner_info = None;
-    {
-        let mut state = state.lock().unwrap();
-        if let Some(uid) = user_id {
-            for table in state.tables.values_mut() {
-                if let Some(game) = &mut table.game {
-                    if let Some(idx) = game.players.iter().position(|p| &p.name == uid) {
-                        match game.player_action(idx, crate::player::PlayerAction::Check) {
-                            Ok(_) => {
-                                result = "You checked\n".to_string();
-                                if game.is_betting_round_complete() {
-                                    round_ended = true;
-                                    match game.current_round {
-                                        crate::game::BettingRound::PreFlop => game.deal_flop(),
-                                        crate::game::BettingRound::Flop => game.deal_turn(),
-                                        crate::game::BettingRound::Turn => game.deal_river(),
-                                        crate::game::BettingRound::River => {
-                                            // Showdown
-                                            let hands = crate::hand::evaluate_hand;
-                                            let mut best: Option<(String, crate::hand::EvaluatedHand)> = None;
-                                            let mut winner = None;
-                                            for player in &game.players {
-                                                let mut all_cards = player.hole_cards.clone();
-                                                all_cards.extend(game.community_cards.iter().cloned());
-                                                let hand = hands(&all_cards);
-                                                if best.is_none() || hand > best.as_ref().unwrap().1 {
-                                                    best = Some((player.name.clone(), hand.clone()));
-                                                    winner = Some(player.name.clone());
-                                                }
-                                            }
-                                            if let Some(winner) = winner {
-                                                winner_info = Some(winner);
-                                            }
-                                        }
-                                        _ => {}
-                                    }
-                                } else {
-                                    game.next_player();
-                                }
-                                next_player_name = game.get_current_player().map(|p| p.name.clone());
-                            }
-                            Err(e) => result = format!("Check error: {}\n", e),
-                        }
-                    }
-                }
-            }
+/// Sets whether the user's losing hands are mucked at showdown instead of shown.
+pub fn handle_auto_muck(user_id: &Option<UserId>, auto_muck: bool, state: &Arc<Mutex<ServerState>>, tx: &UnboundedSender<ServerMessage>) {
+    let Some(uid) = user_id else {
+        let _ = tx.send(ServerMessage::error("You must register first"));
+        return;
+    };
+    let mut state = state.lock().unwrap();
+    let mut found = false;
+    for table in state.tables.values_mut() {
+        if let Some(game) = &mut table.game
+            && let Some(player) = game.players.iter_mut().find(|p| &p.name == uid)
+        {
+            player.auto_muck = auto_muck;
+            found = true;
         }
     }
-    let _ = tx.send(result.clone());
-    send_game_state(state, user_id, tx, next_player_name, round_ended, winner_info).await;
+    let _ = tx.send(match (found, auto_muck) {
+        (false, _) => ServerMessage::error("You are not at a table"),
+        (true, true) => ServerMessage::ok("Losing hands will be mucked"),
+        (true, false) => ServerMessage::ok("Losing hands will be shown"),
+    });
 }
 
-/// Handles a fold action from the user.
-pub async fn handle_fold(user_id: &Option<UserId>, state: &Arc<Mutex<ServerState>>, tx: &UnboundedSender<String>) {
-    let mut result = String::new();
-    let mut next_player_name = None;
-    let mut round_ended = false;
-    let mut winner_info = None;
+/// Lets the table host call a bomb pot for the next hand, optionally with two boards.
+pub fn handle_bomb_pot(user_id: &Option<UserId>, double_board: bool, state: &Arc<Mutex<ServerState>>, tx: &UnboundedSender<ServerMessage>) {
+    let Some(uid) = user_id else {
+        let _ = tx.send(ServerMessage::error("You must register first"));
+        return;
+    };
+    let mut guard = state.lock().unwrap();
+    let state = &mut *guard;
+    let Some(table) = state.users.get(uid)
+        .and_then(|u| u.table.as_ref())
+        .and_then(|t| state.tables.get_mut(t))
+    else {
+        let _ = tx.send(ServerMessage::error("You are not at a table"));
+        return;
+    };
+    if table.host.as_ref() != Some(uid) {
+        let _ = tx.send(ServerMessage::error("Only the table host can call a bomb pot"));
+        return;
+    }
+    let called = match &mut table.game {
+        Some(game) => game.call_bomb_pot(double_board),
+        None => Err("No game at this table yet".to_string()),
+    };
+    match called {
+        Ok(()) => {
+            let message = ServerMessage::Event(TableEvent::BombPotCalled { host: uid.clone(), double_board });
+            send_to_players(&state.writers, &table.audience(), &message);
+        }
+        Err(e) => {
+            let _ = tx.send(ServerMessage::error(format!("Bomb pot error: {}", e)));
+        }
+    }
+}
+
+/// Reveals the board that would have come in the last hand at the user's table,
+/// if it ended early, and shows it to everyone at the table.
+pub fn handle_rabbit(user_id: &Option<UserId>, state: &Arc<Mutex<ServerState>>, tx: &UnboundedSender<ServerMessage>) {
+    let Some(uid) = user_id else {
+        let _ = tx.send(ServerMessage::error("You must register first"));
+        return;
+    };
+    let mut guard = state.lock().unwrap();
+    let state = &mut *guard;
+    let Some(table_id) = state.users.get(uid).and_then(|u| u.table.clone()) else {
+        let _ = tx.send(ServerMessage::error("You are not at a table"));
+        return;
+    };
+    let Some(table) = state.tables.get_mut(&table_id) else {
+        return;
+    };
+    let limit = table.settings.rabbit_hunts;
+    let hunt = match &mut table.game {
+        Some(game) => game.rabbit_hunt(limit),
+        None => Err("There is nothing to rabbit hunt".to_string()),
+    };
+    match hunt {
+        Ok(cards) => {
+            let message = ServerMessage::Event(TableEvent::RabbitHunt { player: uid.clone(), cards });
+            send_to_players(&state.writers, &table.audience(), &message);
+        }
+        Err(e) => {
+            let _ = tx.send(ServerMessage::error(format!("Rabbit error: {}", e)));
+        }
+    }
+}
+
+/// Shows the last completed hand at the user's table, with other players' hidden cards left out.
+pub fn handle_history(user_id: &Option<UserId>, state: &Arc<Mutex<ServerState>>, tx: &UnboundedSender<ServerMessage>) {
+    let Some(uid) = user_id else {
+        let _ = tx.send(ServerMessage::error("You must register first"));
+        return;
+    };
+    let history = {
+        let state = state.lock().unwrap();
+        state.users.get(uid)
+            .and_then(|u| u.table.as_ref())
+            .and_then(|t| state.tables.get(t))
+            .and_then(|t| t.game.as_ref())
+            .and_then(|g| g.last_hand.as_ref())
+            .map(|h| h.view(uid))
+    };
+    match history {
+        Some(events) => {
+            let _ = tx.send(ServerMessage::History { events });
+        }
+        None => {
+            let _ = tx.send(ServerMessage::error("No completed hands at your table yet"));
+        }
+    }
+}
+
+/// Shows the current game state to the user.
+pub async fn handle_show_state(state: &Arc<Mutex<ServerState>>, user_id: &Option<UserId>, tx: &UnboundedSender<ServerMessage>) {
+    send_game_state(state, user_id, tx, false).await;
+}
+
+/// Sends a chat message to everyone at the user's table, players and spectators alike,
+/// or to everyone in the lobby if the user is not at a table.
+pub fn handle_say(user_id: &Option<UserId>, text: &str, state: &Arc<Mutex<ServerState>>, tx: &UnboundedSender<ServerMessage>) {
+    let Some(uid) = user_id else {
+        let _ = tx.send(ServerMessage::error("You must register first"));
+        return;
+    };
+    let state = state.lock().unwrap();
+    let text = match prepare_message(&state.chat_filters, text) {
+        Ok(text) => text,
+        Err(e) => {
+            let _ = tx.send(ServerMessage::error(e));
+            return;
+        }
+    };
+    let Some(user) = state.users.get(uid) else {
+        return;
+    };
+    let (scope, audience) = match user.table.as_ref().or(user.watching.as_ref()).and_then(|t| state.tables.get(t)) {
+        Some(table) => (ChatScope::Table, table.audience()),
+        None => (ChatScope::Lobby, state.users.values()
+            .filter(|u| u.table.is_none() && u.watching.is_none())
+            .map(|u| u.name.clone())
+            .collect()),
+    };
+    let listeners: HashSet<UserId> = audience.into_iter()
+        .filter(|name| !state.users.get(name).is_some_and(|u| u.muted.contains(uid)))
+        .collect();
+    send_to_players(&state.writers, &listeners, &ServerMessage::Chat { from: uid.clone(), scope, text });
+}
+
+/// Sends a private message to one user. A user who muted the sender never gets it,
+/// though the sender is not told so.
+pub fn handle_whisper(user_id: &Option<UserId>, to: &str, text: &str, state: &Arc<Mutex<ServerState>>, tx: &UnboundedSender<ServerMessage>) {
+    let Some(uid) = user_id else {
+        let _ = tx.send(ServerMessage::error("You must register first"));
+        return;
+    };
+    let state = state.lock().unwrap();
+    let Some(target) = state.users.get(to) else {
+        let _ = tx.send(ServerMessage::error(format!("No such user: {}", to)));
+        return;
+    };
+    if to == uid {
+        let _ = tx.send(ServerMessage::error("You cannot whisper to yourself"));
+        return;
+    }
+    let text = match prepare_message(&state.chat_filters, text) {
+        Ok(text) => text,
+        Err(e) => {
+            let _ = tx.send(ServerMessage::error(e));
+            return;
+        }
+    };
+    if !target.muted.contains(uid)
+        && let Some(writer) = state.writers.get(to)
     {
-        let mut state = state.lock().unwrap();
-        if let Some(uid) = user_id {
-            for table in state.tables.values_mut() {
-                if let Some(game) = &mut table.game {
-                    if let Some(idx) = game.players.iter().position(|p| &p.name == uid) {
-                        match game.player_action(idx, crate::player::PlayerAction::Fold) {
-                            Ok(_) => {
-                                result = "You folded\n".to_string();
-                                if game.is_betting_round_complete() {
-                                    round_ended = true;
-                                    match game.current_round {
-                                        crate::game::BettingRound::PreFlop => game.deal_flop(),
-                                        crate::game::BettingRound::Flop => game.deal_turn(),
-                                        crate::game::BettingRound::Turn => game.deal_river(),
-                                        crate::game::BettingRound::River => {
-                                            // Showdown
-                                            let hands = crate::hand::evaluate_hand;
-                                            let mut best: Option<(String, crate::hand::EvaluatedHand)> = None;
-                                            let mut winner = None;
-                                            for player in &game.players {
-                                                let mut all_cards = player.hole_cards.clone();
-                                                all_cards.extend(game.community_cards.iter().cloned());
-                                                let hand = hands(&all_cards);
-                                                if best.is_none() || hand > best.as_ref().unwrap().1 {
-                                                    best = Some((player.name.clone(), hand.clone()));
-                                                    winner = Some(player.name.clone());
-                                                }
-                                            }
-                                            if let Some(winner) = winner {
-                                                winner_info = Some(winner);
-                                            }
-                                        }
-                                        _ => {}
-                                    }
-                                } else {
-                                    game.next_player();
-                                }
-                                next_player_name = game.get_current_player().map(|p| p.name.clone());
-                            }
-                            Err(e) => result = format!("Fold error: {}\n", e),
-                        }
-                    }
-                }
+        let _ = writer.send(ServerMessage::Chat { from: uid.clone(), scope: ChatScope::Whisper, text });
+    }
+    let _ = tx.send(ServerMessage::ok(format!("Whisper sent to {}", to)));
+}
+
+/// Stops or resumes delivering another user's chat to this user.
+pub fn handle_mute(user_id: &Option<UserId>, other: &str, mute: bool, state: &Arc<Mutex<ServerState>>, tx: &UnboundedSender<ServerMessage>) {
+    let Some(uid) = user_id else {
+        let _ = tx.send(ServerMessage::error("You must register first"));
+        return;
+    };
+    let mut state = state.lock().unwrap();
+    if !state.users.contains_key(other) {
+        let _ = tx.send(ServerMessage::error(format!("No such user: {}", other)));
+        return;
+    }
+    if other == uid {
+        let _ = tx.send(ServerMessage::error("You cannot mute yourself"));
+        return;
+    }
+    let Some(user) = state.users.get_mut(uid) else {
+        return;
+    };
+    if mute {
+        user.muted.insert(other.to_string());
+        let _ = tx.send(ServerMessage::ok(format!("Muted {}", other)));
+    } else {
+        user.muted.remove(other);
+        let _ = tx.send(ServerMessage::ok(format!("Unmuted {}", other)));
+    }
+}
+
+/// Moves the game forward after a successful action: passes the turn, deals the next
+/// street once the betting round is complete, or turns the hands over at showdown,
+/// settles the pots and starts the next hand. Once everyone is all-in the remaining streets are dealt
+/// without betting, after a runout vote if the board could be run more than once.
+/// Returns whether the betting round ended and the showdowns of any hands settled.
+pub fn advance_game(game: &mut Game) -> (bool, Vec<Showdown>) {
+    if game.current_round == BettingRound::ShowOrMuck {
+        return if game.next_show_or_muck() { (false, Vec::new()) } else { (true, settle_hand(game)) };
+    }
+    if game.live_player_count() <= 1 {
+        return (true, finish_hand(game));
+    }
+    if !game.is_betting_round_complete() {
+        game.next_player();
+        return (false, Vec::new());
+    }
+    loop {
+        if game.variant.discard_after().as_ref() == Some(&game.current_round) {
+            game.start_discard();
+            return (true, Vec::new());
+        }
+        if game.is_all_in() && game.can_run_multiple() {
+            game.start_runout_vote();
+            return (true, Vec::new());
+        }
+        match game.current_round {
+            BettingRound::Discard => game.finish_discard(),
+            BettingRound::RunoutVote => {
+                game.run_out();
+                return (true, finish_hand(game));
             }
+            BettingRound::PreFlop => game.deal_flop(),
+            BettingRound::Flop => game.deal_turn(),
+            BettingRound::Turn => game.deal_river(),
+            BettingRound::River => return (true, finish_hand(game)),
+            BettingRound::ThirdStreet
+            | BettingRound::FourthStreet
+            | BettingRound::FifthStreet
+            | BettingRound::SixthStreet => game.deal_stud_street(),
+            BettingRound::SeventhStreet => return (true, finish_hand(game)),
+            BettingRound::PostDraw(n) if n >= game.variant.draw_rounds() => return (true, finish_hand(game)),
+            BettingRound::PreDraw | BettingRound::Draw(_) | BettingRound::PostDraw(_) => game.next_draw_phase(),
+            BettingRound::ShowOrMuck | BettingRound::Showdown => {}
+        }
+        // Keep dealing while nobody is left to bet
+        if !(game.is_betting_phase() && game.is_all_in()) {
+            return (true, Vec::new());
         }
     }
-    let _ = tx.send(result.clone());
-    send_game_state(state, user_id, tx, next_player_name, round_ended, winner_info).await;
 }
 
-/// Shows the current game state to the user.
-pub async fn handle_show_state(state: &Arc<Mutex<ServerState>>, user_id: &Option<UserId>, tx: &UnboundedSender<String>) {
-    send_game_state(state, user_id, tx, None, false, None).await;
+/// Ends the hand once the betting is done. Hands are turned over in showdown order,
+/// and the hand waits while a player chooses whether to show or muck.
+fn finish_hand(game: &mut Game) -> Vec<Showdown> {
+    if game.start_show_or_muck() {
+        return Vec::new();
+    }
+    settle_hand(game)
+}
+
+/// Settles the hand at showdown, then deals the next one
+fn settle_hand(game: &mut Game) -> Vec<Showdown> {
+    let results = game.showdown();
+    let mut showdowns = vec![showdown_results(game, &results)];
+    showdowns.extend(deal_next_hand(game));
+    showdowns
+}
+
+/// Deals the next hand, if there are players for it. A hand the antes leave nobody
+/// able to bet in is dealt out at once, so it goes straight to showdown; unless a
+/// player has to choose to show or muck, it is settled here and another one dealt.
+/// The showdowns of such hands are returned in order.
+pub fn deal_next_hand(game: &mut Game) -> Vec<Showdown> {
+    let mut showdowns = Vec::new();
+    while game.start_new_hand().is_ok() && game.current_round == BettingRound::Showdown {
+        if game.start_show_or_muck() {
+            break;
+        }
+        let results = game.showdown();
+        showdowns.push(showdown_results(game, &results));
+    }
+    showdowns
+}
+
+/// Lists what happened at showdown: hands shown or mucked in showdown order, every
+/// board, then who won each pot. Hands are only named when the pot was contested.
+fn showdown_results(game: &Game, results: &[PotResult]) -> Showdown {
+    let contested = game.live_player_count() > 1;
+    let name = |i: usize| game.players[i].name.clone();
+    let reveals = game.reveals.iter()
+        .map(|reveal| RevealedHand { player: name(reveal.player), cards: reveal.cards.clone() })
+        .collect();
+    let pots = results.iter()
+        .map(|result| PotWon {
+            amount: result.amount,
+            run: result.run,
+            high_winners: result.high_winners.iter().map(|&i| name(i)).collect(),
+            low_winners: result.low_winners.iter().map(|&i| name(i)).collect(),
+            payouts: result.payouts.iter()
+                .map(|&(i, amount)| Payout {
+                    player: name(i),
+                    amount,
+                    hand: contested.then(|| game.describe_hand(i, result.run)),
+                })
+                .collect(),
+        })
+        .collect();
+    Showdown { reveals, boards: game.boards.clone(), double_board: game.is_double_board(), pots }
 }
@@ -0,0 +1,315 @@
+use crate::game::ClockRules;
+use clap::Parser;
+use serde::Deserialize;
+use std::net::IpAddr;
+use std::path::PathBuf;
+
+/// Command-line options. Each one overrides the same setting from the config file.
+#[derive(Debug, Default, Parser)]
+#[command(about = "A poker server for text, JSON and WebSocket clients")]
+pub struct Cli {
+    /// TOML file to read settings from
+    #[arg(short, long)]
+    pub config: Option<PathBuf>,
+    /// Address to listen on
+    #[arg(long)]
+    pub bind: Option<String>,
+    /// Port for TCP clients
+    #[arg(long)]
+    pub port: Option<u16>,
+    /// Port for WebSocket clients
+    #[arg(long)]
+    pub websocket_port: Option<u16>,
+    /// Most clients connected at once
+    #[arg(long)]
+    pub max_connections: Option<usize>,
+    /// Most tables open at once
+    #[arg(long)]
+    pub max_tables: Option<usize>,
+    /// Chips a new account starts with
+    #[arg(long)]
+    pub starting_balance: Option<f64>,
+    #[arg(long)]
+    pub small_blind: Option<f64>,
+    #[arg(long)]
+    pub big_blind: Option<f64>,
+    /// Ante for stud games
+    #[arg(long)]
+    pub ante: Option<f64>,
+    /// Seconds a player has to act
+    #[arg(long)]
+    pub action_time: Option<u64>,
+    /// Largest time bank, in seconds
+    #[arg(long)]
+    pub time_bank: Option<u64>,
+    /// Seconds added to every time bank at the start of each hand
+    #[arg(long)]
+    pub time_bank_refill: Option<u64>,
+    /// Seconds a disconnected player has to come back before the game acts for them
+    #[arg(long)]
+    pub disconnect_grace: Option<u64>,
+    /// Hands a disconnected player is dealt before they sit out
+    #[arg(long)]
+    pub sit_out_after: Option<usize>,
+}
+
+/// Server settings, read from a TOML file such as
+///
+/// ```toml
+/// [server]
+/// bind_address = "0.0.0.0"
+/// port = 9000
+///
+/// [game]
+/// starting_balance = 500.0
+///
+/// [timeouts]
+/// action_time = 20
+/// ```
+///
+/// Anything left out keeps its default.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub game: GameConfig,
+    pub timeouts: TimeoutConfig,
+}
+
+/// Where the server listens and how much it takes on
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub bind_address: String,
+    /// Port for TCP clients
+    pub port: u16,
+    /// Port for WebSocket clients
+    pub websocket_port: u16,
+    pub max_connections: usize,
+    pub max_tables: usize,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig { bind_address: "127.0.0.1".to_string(), port: 8080, websocket_port: 8081, max_connections: 1000, max_tables: 100 }
+    }
+}
+
+/// Chips for new accounts and the stakes of new tables
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GameConfig {
+    pub starting_balance: f64,
+    pub small_blind: f64,
+    pub big_blind: f64,
+    /// Ante for stud games; the bring-in is the small blind
+    pub ante: f64,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        GameConfig { starting_balance: 100.0, small_blind: 1.0, big_blind: 2.0, ante: 1.0 }
+    }
+}
+
+/// Default clock for new tables, in seconds; tables can still change it when created
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimeoutConfig {
+    pub action_time: u64,
+    pub time_bank: u64,
+    pub time_bank_refill: u64,
+    pub disconnect_grace: u64,
+    /// Hands a disconnected player is dealt before they sit out; not in seconds
+    pub sit_out_after: usize,
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> Self {
+        let clock = ClockRules::default();
+        TimeoutConfig {
+            action_time: clock.action_secs,
+            time_bank: clock.time_bank_secs,
+            time_bank_refill: clock.refill_secs,
+            disconnect_grace: clock.disconnect_grace_secs,
+            sit_out_after: clock.sit_out_after_hands,
+        }
+    }
+}
+
+impl Config {
+    /// Reads the config file named on the command line, if any, applies the other
+    /// options over it and checks the result
+    pub fn load(cli: &Cli) -> Result<Config, String> {
+        let mut config = match &cli.config {
+            Some(path) => {
+                let text = std::fs::read_to_string(path)
+                    .map_err(|e| format!("Could not read config file {}: {}", path.display(), e))?;
+                Config::parse(&text).map_err(|e| format!("Invalid config file {}: {}", path.display(), e))?
+            }
+            None => Config::default(),
+        };
+        config.apply(cli);
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn parse(text: &str) -> Result<Config, String> {
+        toml::from_str(text).map_err(|e| e.message().to_string())
+    }
+
+    fn apply(&mut self, cli: &Cli) {
+        let server = &mut self.server;
+        let game = &mut self.game;
+        let timeouts = &mut self.timeouts;
+        if let Some(bind) = &cli.bind {
+            server.bind_address = bind.clone();
+        }
+        server.port = cli.port.unwrap_or(server.port);
+        server.websocket_port = cli.websocket_port.unwrap_or(server.websocket_port);
+        server.max_connections = cli.max_connections.unwrap_or(server.max_connections);
+        server.max_tables = cli.max_tables.unwrap_or(server.max_tables);
+        game.starting_balance = cli.starting_balance.unwrap_or(game.starting_balance);
+        game.small_blind = cli.small_blind.unwrap_or(game.small_blind);
+        game.big_blind = cli.big_blind.unwrap_or(game.big_blind);
+        game.ante = cli.ante.unwrap_or(game.ante);
+        timeouts.action_time = cli.action_time.unwrap_or(timeouts.action_time);
+        timeouts.time_bank = cli.time_bank.unwrap_or(timeouts.time_bank);
+        timeouts.time_bank_refill = cli.time_bank_refill.unwrap_or(timeouts.time_bank_refill);
+        timeouts.disconnect_grace = cli.disconnect_grace.unwrap_or(timeouts.disconnect_grace);
+        timeouts.sit_out_after = cli.sit_out_after.unwrap_or(timeouts.sit_out_after);
+    }
+
+    /// Checks the settings make sense together, naming the first one that does not
+    pub fn validate(&self) -> Result<(), String> {
+        let server = &self.server;
+        let game = &self.game;
+        if server.bind_address.parse::<IpAddr>().is_err() {
+            return Err(format!("server.bind_address must be an IP address, not {:?}", server.bind_address));
+        }
+        if server.port != 0 && server.port == server.websocket_port {
+            return Err(format!("server.port and server.websocket_port must differ (both are {})", server.port));
+        }
+        if server.max_connections == 0 {
+            return Err("server.max_connections must be at least 1".to_string());
+        }
+        if server.max_tables == 0 {
+            return Err("server.max_tables must be at least 1".to_string());
+        }
+        if !(game.starting_balance.is_finite() && game.starting_balance > 0.0) {
+            return Err(format!("game.starting_balance must be positive, not {}", game.starting_balance));
+        }
+        if !(game.small_blind.is_finite() && game.small_blind > 0.0) {
+            return Err(format!("game.small_blind must be positive, not {}", game.small_blind));
+        }
+        if !(game.big_blind.is_finite() && game.big_blind >= game.small_blind) {
+            return Err(format!("game.big_blind must be at least the small blind ({}), not {}", game.small_blind, game.big_blind));
+        }
+        if !(game.ante.is_finite() && game.ante >= 0.0) {
+            return Err(format!("game.ante cannot be negative, not {}", game.ante));
+        }
+        let timeouts = &self.timeouts;
+        if timeouts.action_time == 0 {
+            return Err("timeouts.action_time must be at least 1 second".to_string());
+        }
+        if timeouts.disconnect_grace > timeouts.action_time {
+            return Err(format!("timeouts.disconnect_grace ({}) cannot be longer than timeouts.action_time ({})", timeouts.disconnect_grace, timeouts.action_time));
+        }
+        if timeouts.time_bank_refill > timeouts.time_bank {
+            return Err(format!("timeouts.time_bank_refill ({}) cannot be more than timeouts.time_bank ({})", timeouts.time_bank_refill, timeouts.time_bank));
+        }
+        Ok(())
+    }
+
+    /// The clock new tables start with
+    pub fn clock_rules(&self) -> ClockRules {
+        let timeouts = &self.timeouts;
+        ClockRules {
+            action_secs: timeouts.action_time,
+            time_bank_secs: timeouts.time_bank,
+            refill_secs: timeouts.time_bank_refill,
+            disconnect_grace_secs: timeouts.disconnect_grace,
+            sit_out_after_hands: timeouts.sit_out_after,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::{BANNER, SERVER_FULL};
+    use crate::test_support::*;
+
+    #[test]
+    fn test_config_file_fills_in_defaults_and_options_override_it() {
+        let mut config = Config::parse("[server]\nport = 9000\n\n[timeouts]\naction_time = 20\nsit_out_after = 3\n").unwrap();
+        assert_eq!(config.server.port, 9000);
+        assert_eq!(config.server.websocket_port, 8081);
+        assert_eq!(config.game, GameConfig::default());
+        assert_eq!(config.clock_rules().action_secs, 20);
+        assert_eq!(config.clock_rules().sit_out_after_hands, 3);
+        config.apply(&Cli { port: Some(7000), big_blind: Some(4.0), time_bank_refill: Some(5), sit_out_after: Some(4), ..Cli::default() });
+        assert_eq!(config.server.port, 7000);
+        assert_eq!(config.game.big_blind, 4.0);
+        assert_eq!(config.clock_rules().refill_secs, 5);
+        assert_eq!(config.clock_rules().sit_out_after_hands, 4);
+        assert_eq!(config.validate(), Ok(()));
+        assert_eq!(Config::default().validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_bad_config_is_rejected_with_the_setting_named() {
+        assert!(Config::parse("[server]\nprot = 9000\n").unwrap_err().contains("prot"));
+        assert!(Config::parse("[game]\nsmall_blind = \"one\"\n").is_err());
+        let invalid = |text: &str| Config::parse(text).unwrap().validate().unwrap_err();
+        assert!(invalid("[server]\nbind_address = \"localhost:80\"\n").starts_with("server.bind_address"));
+        assert!(invalid("[server]\nwebsocket_port = 8080\n").starts_with("server.port"));
+        assert!(invalid("[server]\nmax_tables = 0\n").starts_with("server.max_tables"));
+        assert!(invalid("[game]\nsmall_blind = 5.0\n").starts_with("game.big_blind"));
+        assert!(invalid("[game]\nstarting_balance = -1.0\n").starts_with("game.starting_balance"));
+        assert!(invalid("[timeouts]\naction_time = 0\n").starts_with("timeouts.action_time"));
+    }
+
+    #[test]
+    fn test_timeouts_must_agree_with_each_other() {
+        let invalid = |text: &str| Config::parse(text).unwrap().validate().unwrap_err();
+        assert!(invalid("[timeouts]\naction_time = 10\ndisconnect_grace = 11\n").starts_with("timeouts.disconnect_grace"));
+        assert!(invalid("[timeouts]\ntime_bank = 10\ntime_bank_refill = 11\n").starts_with("timeouts.time_bank_refill"));
+        Config::parse("[timeouts]\naction_time = 10\ndisconnect_grace = 10\ntime_bank = 10\ntime_bank_refill = 10\n").unwrap().validate().unwrap();
+        // Options given on the command line are checked against the file's settings
+        let mut config = Config::parse("[timeouts]\naction_time = 10\n").unwrap();
+        config.apply(&Cli { disconnect_grace: Some(20), ..Cli::default() });
+        assert!(config.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_server_limits_come_from_the_config() {
+        let mut config = Config::default();
+        config.server.max_connections = 1;
+        config.server.max_tables = 1;
+        config.game.starting_balance = 250.0;
+        let url = start_gateway_with(config).await;
+        let mut alice = json_client(&url, "alice").await;
+        alice.send(Message::text(r#"{"type":"show"}"#)).await.unwrap();
+        wait_for(&mut alice, r#""balance":250.0"#).await;
+        alice.send(Message::text(r#"{"type":"create_table","table":"t1"}"#)).await.unwrap();
+        wait_for(&mut alice, "table_created").await;
+        alice.send(Message::text(r#"{"type":"create_table","table":"t2"}"#)).await.unwrap();
+        wait_for(&mut alice, "most tables").await;
+
+        let (mut turned_away, _) = connect_async(&url).await.unwrap();
+        assert_eq!(next_frame(&mut turned_away).await, SERVER_FULL);
+        // The slot is freed once the first client leaves
+        drop(alice);
+        let mut bob = tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            loop {
+                let (mut stream, _) = connect_async(&url).await.unwrap();
+                if next_frame(&mut stream).await == BANNER[0] {
+                    return stream;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            }
+        }).await.unwrap();
+        next_frame(&mut bob).await;
+    }
+}
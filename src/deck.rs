@@ -1,14 +1,33 @@
 use crate::card::{Card, Rank, Suit};
+use crate::variant::GameVariant;
 use rand::seq::SliceRandom;
 use rand::thread_rng;
 
 #[derive(Debug, Clone)]
 pub struct Deck {
     cards: Vec<Card>,
+    /// Cards thrown away during draws, reshuffled in when the deck runs short
+    discards: Vec<Card>,
 }
 
 impl Deck {
-    pub fn new_shuffled() -> Self {
+    /// Builds and shuffles the deck used by the given variant
+    pub fn new_shuffled(variant: &dyn GameVariant) -> Self {
+        let mut cards = variant.deck();
+        cards.shuffle(&mut thread_rng());
+        Self { cards, discards: Vec::new() }
+    }
+
+    /// Up to two jokers, told apart by suit
+    pub fn jokers(count: usize) -> Vec<Card> {
+        [Suit::Spades, Suit::Hearts].iter()
+            .take(count)
+            .map(|&suit| Card { rank: Rank::Joker, suit })
+            .collect()
+    }
+
+    /// The four suits of every rank from `lowest` up to the ace
+    pub fn standard_cards(lowest: Rank) -> Vec<Card> {
         let mut cards = Vec::new();
         for &suit in &[Suit::Clubs, Suit::Diamonds, Suit::Hearts, Suit::Spades] {
             for &rank in &[
@@ -26,14 +45,77 @@ impl Deck {
                 Rank::King,
                 Rank::Ace,
             ] {
-                cards.push(Card { rank, suit });
+                if rank >= lowest {
+                    cards.push(Card { rank, suit });
+                }
             }
         }
-        cards.shuffle(&mut thread_rng());
-        Self { cards }
+        cards
     }
 
     pub fn draw(&mut self) -> Option<Card> {
         self.cards.pop()
     }
+
+    /// Number of cards left to deal
+    pub fn remaining(&self) -> usize {
+        self.cards.len()
+    }
+
+    /// Adds discarded cards to the discard pile
+    pub fn discard(&mut self, cards: &[Card]) {
+        self.discards.extend_from_slice(cards);
+    }
+
+    /// Shuffles the discard pile back in under the remaining cards
+    pub fn reshuffle_discards(&mut self) {
+        let mut discards = std::mem::take(&mut self.discards);
+        discards.shuffle(&mut thread_rng());
+        // Cards are drawn from the end, so the old stub is dealt first
+        discards.append(&mut self.cards);
+        self.cards = discards;
+    }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::variant::{ShortDeck, TexasHoldem};
+
+    #[test]
+    fn test_short_deck_has_36_cards() {
+        let mut deck = Deck::new_shuffled(&ShortDeck { trips_beat_straight: true });
+        let mut count = 0;
+        while let Some(card) = deck.draw() {
+            assert!(card.rank >= Rank::Six);
+            count += 1;
+        }
+        assert_eq!(count, 36);
+        assert_eq!(Deck::new_shuffled(&TexasHoldem).cards.len(), 52);
+    }
+
+    #[test]
+    fn test_reshuffle_brings_discards_back() {
+        let mut deck = Deck::new_shuffled(&TexasHoldem);
+        let mut drawn = Vec::new();
+        while deck.remaining() > 2 {
+            drawn.push(deck.draw().unwrap());
+        }
+        let stub: Vec<Card> = deck.cards.clone();
+        deck.discard(&drawn[..10]);
+        deck.reshuffle_discards();
+        assert_eq!(deck.remaining(), 12);
+        assert_eq!(deck.draw(), Some(stub[1]));
+        assert_eq!(deck.draw(), Some(stub[0]));
+    }
+
+    #[test]
+    fn test_jokers_are_distinct_cards() {
+        let jokers = Deck::jokers(2);
+        assert_eq!(jokers.len(), 2);
+        assert_ne!(jokers[0], jokers[1]);
+        assert!(jokers.iter().all(|c| c.rank == Rank::Joker));
+        assert_eq!(Deck::jokers(1).len(), 1);
+    }
+}
+
@@ -1,6 +1,12 @@
 use crate::card::Card;
 use crate::deck::Deck;
+use crate::history::{ActionTaken, HandEvent, HandHistory};
+use crate::hand::{evaluate_razz, evaluate_visible, low_value, EvaluatedHand, LowHand};
 use crate::player::{Player, PlayerAction, PlayerState};
+use crate::variant::{BettingStructure, GameVariant, PotSplit};
+use std::cmp::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum BettingRound {
@@ -8,11 +14,112 @@ pub enum BettingRound {
     Flop,
     Turn,
     River,
+    /// Stud streets: two down cards and one up card, then one card per street
+    ThirdStreet,
+    FourthStreet,
+    FifthStreet,
+    SixthStreet,
+    SeventhStreet,
+    /// Pineapple: every player discards one of their three hole cards
+    Discard,
+    /// Draw poker: the betting round before the first draw
+    PreDraw,
+    /// Draw poker: the n-th draw (counting from 1), where players replace cards
+    Draw(usize),
+    /// Draw poker: the betting round after the n-th draw
+    PostDraw(usize),
+    /// Everyone is all-in before the board is complete: each player votes how many
+    /// times to run the rest of the board
+    RunoutVote,
+    /// Hands are turned over in showdown order; a player who may muck a losing hand
+    /// chooses whether to show it
+    ShowOrMuck,
     Showdown,
 }
 
+/// Outcome of a single pot (main or side pot) at showdown
+#[derive(Debug, Clone, PartialEq)]
+pub struct PotResult {
+    pub amount: f64,
+    /// Indices of the players sharing the high half (or the whole pot)
+    pub high_winners: Vec<usize>,
+    /// Indices of the players sharing the low half, empty when no low qualifies
+    pub low_winners: Vec<usize>,
+    /// Chips paid to each player from this pot
+    pub payouts: Vec<(usize, f64)>,
+    /// Which run of the board this share of the pot was played on (0 unless run more than once)
+    pub run: usize,
+}
+
+/// A hand at showdown, in the order hands were turned over
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reveal {
+    pub player: usize,
+    /// The cards shown, or `None` if the hand was mucked
+    pub cards: Option<Vec<Card>>,
+}
+
+/// How a hand begins
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HandStart {
+    /// Blinds (or antes and the bring-in in stud), then betting before the flop
+    Normal,
+    /// Everyone antes, betting before the flop is skipped and the hand starts on
+    /// the flop; with `double_board` every pot is split between two boards
+    BombPot { ante: f64, double_board: bool },
+}
+
+/// When a table plays bomb pots on its own, and how big they are
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BombPotRules {
+    /// Play a bomb pot every this many hands (0 only plays them when called)
+    pub every: usize,
+    /// Amount every player antes into a bomb pot
+    pub ante: f64,
+    /// Whether scheduled bomb pots use two boards
+    pub double_board: bool,
+}
+
+impl Default for BombPotRules {
+    fn default() -> Self {
+        BombPotRules { every: 0, ante: 5.0, double_board: false }
+    }
+}
+
+/// How long players have to act, and the time bank they can fall back on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClockRules {
+    /// Seconds a player has to act before their time bank starts
+    pub action_secs: u64,
+    /// Largest time bank a player can hold, in seconds
+    pub time_bank_secs: u64,
+    /// Seconds added to every player's time bank at the start of each hand
+    pub refill_secs: u64,
+    /// Seconds a disconnected player has to come back before the game acts for them
+    pub disconnect_grace_secs: u64,
+    /// Hands a disconnected player is dealt before they sit out
+    pub sit_out_after_hands: usize,
+}
+
+impl Default for ClockRules {
+    fn default() -> Self {
+        ClockRules { action_secs: 15, time_bank_secs: 30, refill_secs: 2, disconnect_grace_secs: 5, sit_out_after_hands: 2 }
+    }
+}
+
+/// What was left of a hand that ended before its board was complete, kept so the
+/// undealt cards can be revealed afterwards without touching the result
+#[derive(Debug, Clone)]
+pub struct Rabbit {
+    board: Vec<Card>,
+    deck: Deck,
+    /// Times the board has been revealed so far
+    pub hunts: usize,
+}
+
 #[derive(Debug, Clone)]
 pub struct Game {
+    pub variant: Arc<dyn GameVariant>,
     pub players: Vec<Player>,
     pub deck: Deck,
     pub current_player: usize,
@@ -23,14 +130,68 @@ pub struct Game {
     pub dealer_position: usize,
     pub small_blind: f64,
     pub big_blind: f64,
+    /// Ante posted by every player at the start of a hand
+    pub ante: f64,
+    /// Forced opening bet in stud, posted by the player showing the lowest card
+    pub bring_in: f64,
     pub active_players: Vec<usize>,
+    pub hand_in_progress: bool,
+    /// Bets and raises made in the current betting round, for the fixed-limit cap
+    pub bets_this_round: usize,
+    /// Record of the hand in progress
+    pub history: HandHistory,
+    /// Record of the last completed hand
+    pub last_hand: Option<HandHistory>,
+    /// Votes cast during the runout vote: player index and number of runs asked for
+    pub run_votes: Vec<(usize, usize)>,
+    /// Complete boards when the board was run more than once, empty otherwise
+    pub boards: Vec<Vec<Card>>,
+    /// The last hand, if it ended before the board was complete, until the next is dealt
+    pub rabbit: Option<Rabbit>,
+    /// How the current hand started
+    pub hand_start: HandStart,
+    /// Bomb pot rules for the table
+    pub bomb_pots: BombPotRules,
+    /// A bomb pot called for the next hand, with whether it uses two boards
+    pub bomb_pot_called: Option<bool>,
+    /// Hands dealt so far, for bomb pots every N hands
+    pub hands_dealt: usize,
+    /// Actions and runout votes accepted this hand; each one ends a turn
+    pub actions_taken: usize,
+    /// Last player to bet or raise on the final betting round; they show first
+    pub last_aggressor: Option<usize>,
+    /// Whether every hand is shown at showdown once a player is all-in
+    pub all_in_face_up: bool,
+    /// Hands shown or mucked at the last showdown, in showdown order
+    pub reveals: Vec<Reveal>,
+    /// Hole cards each player held in the last hand, which they may still show
+    pub last_hole_cards: Vec<(String, Vec<Card>)>,
+    /// Players who have already shown cards from the last hand; each may show once
+    pub shown_last_hand: Vec<String>,
+    /// When the last hand ended
+    pub hand_ended_at: Option<Instant>,
+    /// Action clock and time bank rules for the table
+    pub clock: ClockRules,
 }
 
+/// Maximum number of bets (a bet and three raises) per round in fixed-limit games
+pub const FIXED_LIMIT_CAP: usize = 4;
+
+/// Maximum number of times the board can be run when everyone is all-in
+pub const MAX_RUNS: usize = 3;
+
+/// How long after a hand players can still show their cards from it
+pub const SHOW_CARDS_WINDOW: Duration = Duration::from_secs(15);
+
+/// Pots are split to the cent; this is the smallest amount the odd-chip rule hands out
+const UNITS_PER_CHIP: f64 = 100.0;
+
 impl Game {
-    pub fn new(small_blind: f64, big_blind: f64) -> Self {
+    pub fn new(variant: Arc<dyn GameVariant>, small_blind: f64, big_blind: f64) -> Self {
         Self {
+            deck: Deck::new_shuffled(variant.as_ref()),
+            variant,
             players: Vec::new(),
-            deck: Deck::new_shuffled(),
             current_player: 0,
             current_round: BettingRound::PreFlop,
             community_cards: Vec::new(),
@@ -39,34 +200,97 @@ impl Game {
             dealer_position: 0,
             small_blind,
             big_blind,
+            ante: 0.0,
+            bring_in: small_blind,
             active_players: Vec::new(),
+            hand_in_progress: false,
+            bets_this_round: 0,
+            history: HandHistory::default(),
+            last_hand: None,
+            run_votes: Vec::new(),
+            boards: Vec::new(),
+            rabbit: None,
+            hand_start: HandStart::Normal,
+            bomb_pots: BombPotRules::default(),
+            bomb_pot_called: None,
+            hands_dealt: 0,
+            actions_taken: 0,
+            last_aggressor: None,
+            all_in_face_up: false,
+            reveals: Vec::new(),
+            last_hole_cards: Vec::new(),
+            shown_last_hand: Vec::new(),
+            hand_ended_at: None,
+            clock: ClockRules::default(),
         }
     }
 
     pub fn add_player(&mut self, name: String, balance: f64) {
-        let player = Player::new(name, balance);
+        let mut player = Player::new(name, balance);
+        player.time_bank = self.clock.time_bank_secs;
+        // Players joining mid-hand wait for the next deal
+        if self.hand_in_progress {
+            player.state = PlayerState::SittingOut;
+        }
         self.players.push(player);
     }
 
     pub fn start_new_hand(&mut self) -> Result<(), String> {
-        if self.players.len() < 2 {
+        // Disconnected players are dealt in for a few hands before they sit out, and
+        // players with no chips left sit out straight away
+        for player in &mut self.players {
+            if (player.disconnected_at.is_some() && player.missed_hands >= self.clock.sit_out_after_hands) || player.balance <= 0.0 {
+                player.sitting_out = true;
+            }
+        }
+        if self.players.iter().filter(|p| !p.sitting_out).count() < 2 {
             return Err("Need at least 2 players to start a hand".to_string());
         }
+        for player in self.players.iter_mut().filter(|p| p.disconnected_at.is_some() && !p.sitting_out) {
+            player.missed_hands += 1;
+        }
         
         // Reset game state
-        self.deck = Deck::new_shuffled();
+        self.deck = Deck::new_shuffled(self.variant.as_ref());
         self.community_cards.clear();
+        self.run_votes.clear();
+        self.boards.clear();
+        self.reveals.clear();
+        // The last hand's undealt board is gone once new cards are dealt
+        self.rabbit = None;
+        self.last_aggressor = None;
         self.pot = 0.0;
         self.current_bet = 0.0;
-        self.current_round = BettingRound::PreFlop;
+        self.bets_this_round = 0;
+        self.current_round = if self.variant.is_draw() { BettingRound::PreDraw } else { BettingRound::PreFlop };
+        self.hand_in_progress = true;
         
         // Reset all players
         for player in &mut self.players {
             player.reset_for_new_hand();
+            player.time_bank = (player.time_bank + self.clock.refill_secs).min(self.clock.time_bank_secs);
         }
+        self.history = HandHistory::default();
+        self.history.record(HandEvent::Started {
+            variant: self.variant.name().to_string(),
+            players: self.players.iter().filter(|p| !p.sitting_out).map(|p| p.name.clone()).collect(),
+        });
         
         // Move dealer button
-        self.dealer_position = (self.dealer_position + 1) % self.players.len();
+        self.dealer_position = self.next_seated(self.dealer_position);
+        self.hands_dealt += 1;
+        self.actions_taken = 0;
+        self.hand_start = self.next_hand_start();
+        
+        if let HandStart::BombPot { ante, double_board } = self.hand_start {
+            self.start_bomb_pot(ante, double_board);
+            return Ok(());
+        }
+        
+        if self.variant.is_stud() {
+            self.start_stud_hand();
+            return Ok(());
+        }
         
         // Post blinds
         self.post_blinds();
@@ -78,40 +302,267 @@ impl Game {
         self.update_active_players();
         
         // Set first player to act (after big blind)
-        self.current_player = (self.dealer_position + 3) % self.players.len();
+        self.current_player = self.next_seated(self.next_seated(self.next_seated(self.dealer_position)));
         
         Ok(())
     }
 
+    /// The next seat after `seat` whose player is not sitting out
+    fn next_seated(&self, seat: usize) -> usize {
+        (1..=self.players.len())
+            .map(|i| (seat + i) % self.players.len())
+            .find(|&i| !self.players[i].sitting_out)
+            .unwrap_or((seat + 1) % self.players.len())
+    }
+
+    /// Marks a player as having lost or regained their connection. A player who comes
+    /// back is dealt in again from the next hand.
+    pub fn set_disconnected(&mut self, name: &str, disconnected: bool) {
+        if let Some(player) = self.players.iter_mut().find(|p| p.name == name) {
+            if !disconnected {
+                player.disconnected_at = None;
+                player.missed_hands = 0;
+                player.sitting_out = false;
+            } else if player.disconnected_at.is_none() {
+                player.disconnected_at = Some(Instant::now());
+            }
+        }
+    }
+
+    /// Calls a bomb pot for the next hand. Only variants that deal a flop can play one.
+    pub fn call_bomb_pot(&mut self, double_board: bool) -> Result<(), String> {
+        if self.variant.board_deals().is_empty() {
+            return Err(format!("{} has no flop to start a bomb pot on", self.variant.name()));
+        }
+        self.bomb_pot_called = Some(double_board);
+        Ok(())
+    }
+
+    /// Decides how the hand being dealt starts: a called bomb pot, a scheduled one, or a normal hand
+    fn next_hand_start(&mut self) -> HandStart {
+        let scheduled = self.bomb_pots.every > 0 && self.hands_dealt.is_multiple_of(self.bomb_pots.every);
+        let double_board = match self.bomb_pot_called.take() {
+            Some(double_board) => double_board,
+            None if scheduled => self.bomb_pots.double_board,
+            None => return HandStart::Normal,
+        };
+        if self.variant.board_deals().is_empty() {
+            return HandStart::Normal;
+        }
+        HandStart::BombPot { ante: self.bomb_pots.ante, double_board }
+    }
+
+    /// Bomb pot: everyone antes, gets their hole cards and the hand goes straight to
+    /// the flop (after the discard in Pineapple). The first player left of the button acts first.
+    fn start_bomb_pot(&mut self, ante: f64, double_board: bool) {
+        for player in &mut self.players {
+            if player.state != PlayerState::Active {
+                continue;
+            }
+            let ante = ante.min(player.balance);
+            if player.bet(ante).is_ok() {
+                self.pot += ante;
+                self.history.record(HandEvent::Posted { player: player.name.clone(), amount: ante, kind: "bomb pot ante" });
+                // A short stack that covers only part of the ante is all-in for it
+                if player.balance == 0.0 {
+                    player.state = PlayerState::AllIn;
+                }
+            }
+        }
+        self.deal_hole_cards();
+        self.update_active_players();
+        if double_board {
+            self.boards = vec![Vec::new(), Vec::new()];
+        }
+        if self.variant.discard_after() == Some(BettingRound::PreFlop) {
+            self.start_discard();
+        } else {
+            self.deal_flop();
+            if self.is_all_in() {
+                self.deal_out();
+                return;
+            }
+            self.current_player = self.dealer_position;
+            self.next_player();
+        }
+    }
+
+    /// Third street: antes, two down cards and one up card each, then the bring-in
+    fn start_stud_hand(&mut self) {
+        self.current_round = BettingRound::ThirdStreet;
+        self.post_antes();
+        self.deal_hole_cards();
+        for player in self.players.iter_mut().filter(|p| !p.sitting_out) {
+            if let Some(card) = self.deck.draw() {
+                player.add_up_card(card);
+                self.history.record(HandEvent::UpCards { player: player.name.clone(), cards: vec![card] });
+            }
+        }
+        self.update_active_players();
+        if self.is_all_in() {
+            self.deal_out();
+            return;
+        }
+        self.post_bring_in();
+    }
+
+    /// Deals the rest of the hand without any betting, for when the antes leave at most
+    /// one player with chips behind. The hand then waits to be settled at showdown.
+    fn deal_out(&mut self) {
+        if self.variant.is_stud() {
+            while self.current_round != BettingRound::SeventhStreet {
+                self.deal_stud_street();
+            }
+        } else {
+            self.run_out();
+        }
+        self.current_round = BettingRound::Showdown;
+    }
+
+    fn post_antes(&mut self) {
+        if self.ante <= 0.0 {
+            return;
+        }
+        for player in self.players.iter_mut().filter(|p| !p.sitting_out) {
+            let ante = self.ante.min(player.balance);
+            if player.bet(ante).is_ok() {
+                self.pot += ante;
+                self.history.record(HandEvent::Posted { player: player.name.clone(), amount: ante, kind: "ante" });
+                if player.balance == 0.0 {
+                    player.state = PlayerState::AllIn;
+                }
+            }
+        }
+        self.current_bet = self.highest_commitment();
+    }
+
+    /// The lowest up card brings it in (suits break ties, clubs lowest); in lowball
+    /// the highest up card does, with aces low. Action continues to that player's left.
+    fn post_bring_in(&mut self) {
+        let showing = self.players.iter().enumerate()
+            .filter(|(_, p)| p.state == PlayerState::Active && !p.up_cards.is_empty());
+        let bring_in_pos = if self.variant.plays_high() {
+            showing.min_by_key(|(_, p)| p.up_cards[0]).map(|(i, _)| i)
+        } else {
+            showing.max_by_key(|(_, p)| (low_value(p.up_cards[0].rank), p.up_cards[0].suit)).map(|(i, _)| i)
+        };
+        let Some(pos) = bring_in_pos else {
+            return;
+        };
+        let amount = self.bring_in.min(self.players[pos].balance);
+        if amount > 0.0 && self.players[pos].bet(amount).is_ok() {
+            self.pot += amount;
+            self.history.record(HandEvent::Posted { player: self.players[pos].name.clone(), amount, kind: "bring-in" });
+            self.current_bet = self.highest_commitment();
+            self.current_player = pos;
+            self.next_player();
+        } else {
+            self.current_player = pos;
+        }
+    }
+
+    /// Deals the next stud street and starts its betting round. Fourth through sixth
+    /// street are dealt face up and seventh street face down. If the deck cannot give
+    /// every remaining player a seventh-street card, a single community card is dealt
+    /// face up instead and plays for everyone.
+    pub fn deal_stud_street(&mut self) {
+        let next_round = match self.current_round {
+            BettingRound::ThirdStreet => BettingRound::FourthStreet,
+            BettingRound::FourthStreet => BettingRound::FifthStreet,
+            BettingRound::FifthStreet => BettingRound::SixthStreet,
+            BettingRound::SixthStreet => BettingRound::SeventhStreet,
+            _ => return,
+        };
+        let live: Vec<usize> = self.active_players.clone();
+        // Burn one card, as long as the deck can spare it
+        if self.deck.remaining() > live.len() {
+            self.deck.draw();
+        }
+        
+        if next_round == BettingRound::SeventhStreet && self.deck.remaining() < live.len() {
+            if let Some(card) = self.deck.draw() {
+                self.community_cards.push(card);
+                self.history.record(HandEvent::Board { cards: vec![card] });
+            }
+        } else {
+            for &i in &live {
+                if let Some(card) = self.deck.draw() {
+                    let player = self.players[i].name.clone();
+                    if next_round == BettingRound::SeventhStreet {
+                        self.players[i].add_card(card);
+                        self.history.record(HandEvent::HoleCards { player, cards: vec![card] });
+                    } else {
+                        self.players[i].add_up_card(card);
+                        self.history.record(HandEvent::UpCards { player, cards: vec![card] });
+                    }
+                }
+            }
+        }
+        
+        self.start_betting_round(next_round);
+        if let Some(first) = self.best_visible_hand() {
+            self.current_player = first;
+        }
+    }
+
+    /// The active player showing the best hand on their up cards (the highest hand,
+    /// or the lowest in lowball), who acts first on fourth street and later
+    fn best_visible_hand(&self) -> Option<usize> {
+        let candidates: Vec<usize> = self.players.iter().enumerate()
+            .filter(|(_, p)| p.state == PlayerState::Active)
+            .map(|(i, _)| i)
+            .collect();
+        let candidates = self.order_from_button(candidates);
+        if !self.variant.plays_high() {
+            return candidates.into_iter().min_by_key(|&i| evaluate_razz(&self.players[i].up_cards));
+        }
+        let ranking = self.variant.ranking();
+        let mut best: Option<(usize, EvaluatedHand)> = None;
+        for i in candidates {
+            let visible = evaluate_visible(&self.players[i].up_cards);
+            if best.as_ref().is_none_or(|(_, b)| ranking.compare(&visible, b) == Ordering::Greater) {
+                best = Some((i, visible));
+            }
+        }
+        best.map(|(i, _)| i)
+    }
+
     fn post_blinds(&mut self) {
-        let small_blind_pos = (self.dealer_position + 1) % self.players.len();
-        let big_blind_pos = (self.dealer_position + 2) % self.players.len();
+        let small_blind_pos = self.next_seated(self.dealer_position);
+        let big_blind_pos = self.next_seated(small_blind_pos);
         
         // Post small blind
-        if let Some(player) = self.players.get_mut(small_blind_pos) {
-            if let Ok(_) = player.bet(self.small_blind) {
-                self.pot += self.small_blind;
-            }
+        if let Some(player) = self.players.get_mut(small_blind_pos)
+            && player.bet(self.small_blind).is_ok()
+        {
+            self.pot += self.small_blind;
+            self.history.record(HandEvent::Posted { player: player.name.clone(), amount: self.small_blind, kind: "small blind" });
         }
         
         // Post big blind
-        if let Some(player) = self.players.get_mut(big_blind_pos) {
-            if let Ok(_) = player.bet(self.big_blind) {
-                self.pot += self.big_blind;
-                self.current_bet = self.big_blind;
-            }
+        if let Some(player) = self.players.get_mut(big_blind_pos)
+            && player.bet(self.big_blind).is_ok()
+        {
+            self.pot += self.big_blind;
+            self.current_bet = self.big_blind;
+            self.history.record(HandEvent::Posted { player: player.name.clone(), amount: self.big_blind, kind: "big blind" });
+            // The big blind counts as the first bet
+            self.bets_this_round = 1;
         }
     }
 
     fn deal_hole_cards(&mut self) {
-        // Deal 2 cards to each player
-        for _ in 0..2 {
-            for player in &mut self.players {
+        // Deal the variant's number of hole cards to each player
+        for _ in 0..self.variant.hole_card_count() {
+            for player in self.players.iter_mut().filter(|p| !p.sitting_out) {
                 if let Some(card) = self.deck.draw() {
                     player.add_card(card);
                 }
             }
         }
+        for player in self.players.iter().filter(|p| !p.sitting_out) {
+            self.history.record(HandEvent::HoleCards { player: player.name.clone(), cards: player.hole_cards.clone() });
+        }
     }
 
     fn update_active_players(&mut self) {
@@ -125,52 +576,94 @@ impl Game {
 
     pub fn deal_flop(&mut self) {
         if self.current_round == BettingRound::PreFlop {
-            // Burn one card
-            self.deck.draw();
-            
-            // Deal 3 community cards
-            for _ in 0..3 {
-                if let Some(card) = self.deck.draw() {
-                    self.community_cards.push(card);
-                }
-            }
-            
-            self.current_round = BettingRound::Flop;
-            self.current_bet = 0.0;
-            self.reset_player_actions();
+            self.deal_board(0);
+            self.start_betting_round(BettingRound::Flop);
         }
     }
 
     pub fn deal_turn(&mut self) {
         if self.current_round == BettingRound::Flop {
-            // Burn one card
-            self.deck.draw();
-            
-            // Deal 1 community card
-            if let Some(card) = self.deck.draw() {
-                self.community_cards.push(card);
-            }
-            
-            self.current_round = BettingRound::Turn;
-            self.current_bet = 0.0;
-            self.reset_player_actions();
+            self.deal_board(1);
+            self.start_betting_round(BettingRound::Turn);
         }
     }
 
     pub fn deal_river(&mut self) {
         if self.current_round == BettingRound::Turn {
-            // Burn one card
-            self.deck.draw();
-            
-            // Deal 1 community card
+            self.deal_board(2);
+            self.start_betting_round(BettingRound::River);
+        }
+    }
+
+    /// Burns a card and deals the community cards the variant schedules for the
+    /// given street (0 = flop, 1 = turn, 2 = river)
+    fn deal_board(&mut self, street: usize) {
+        let count = self.variant.board_deals().get(street).copied().unwrap_or(0);
+        if count == 0 {
+            return;
+        }
+        if self.is_double_board() {
+            // Each board gets its own burn and cards; the first board is the community cards
+            for board in 0..self.boards.len() {
+                self.deck.draw();
+                let mut dealt = Vec::new();
+                for _ in 0..count {
+                    if let Some(card) = self.deck.draw() {
+                        self.boards[board].push(card);
+                        dealt.push(card);
+                    }
+                }
+                self.history.record(HandEvent::DoubleBoard { board: board + 1, cards: dealt });
+            }
+            self.community_cards = self.boards[0].clone();
+            return;
+        }
+        self.deck.draw();
+        let mut dealt = Vec::new();
+        for _ in 0..count {
             if let Some(card) = self.deck.draw() {
                 self.community_cards.push(card);
+                dealt.push(card);
             }
-            
-            self.current_round = BettingRound::River;
-            self.current_bet = 0.0;
-            self.reset_player_actions();
         }
+        self.history.record(HandEvent::Board { cards: dealt });
+    }
+
+    /// Starts a new betting round. Bets are tracked as each player's total for the
+    /// hand, so the level to call carries over from the previous street.
+    fn start_betting_round(&mut self, round: BettingRound) {
+        self.current_round = round;
+        self.current_bet = self.highest_commitment();
+        self.bets_this_round = 0;
+        // Streets dealt after everyone is all-in keep the aggressor from the last betting
+        if !self.is_all_in() {
+            self.last_aggressor = None;
+        }
+        self.reset_player_actions();
+    }
+
+    /// The fixed bet size on the current street, or `None` in no-limit games.
+    /// The small bet (the big blind) is used on the early streets and the big
+    /// bet (twice that) from the turn, fifth street or the second draw on.
+    pub fn fixed_limit_bet(&self) -> Option<f64> {
+        if self.variant.betting_structure() != BettingStructure::FixedLimit {
+            return None;
+        }
+        let small_street = matches!(
+            self.current_round,
+            BettingRound::PreFlop
+                | BettingRound::Flop
+                | BettingRound::ThirdStreet
+                | BettingRound::FourthStreet
+                | BettingRound::PreDraw
+                | BettingRound::PostDraw(1)
+        );
+        Some(if small_street { self.big_blind } else { self.big_blind * 2.0 })
+    }
+
+    /// The most chips any player has committed this hand
+    fn highest_commitment(&self) -> f64 {
+        self.players.iter().map(|p| p.chips_in_play).fold(0.0, f64::max)
     }
 
     fn reset_player_actions(&mut self) {
@@ -179,51 +672,156 @@ impl Game {
         }
     }
 
-    pub fn player_action(&mut self, player_index: usize, action: PlayerAction) -> Result<(), String> {
+    /// Applies a player's action and returns it as the table sees it
+    pub fn player_action(&mut self, player_index: usize, action: PlayerAction) -> Result<ActionTaken, String> {
         if player_index >= self.players.len() {
             return Err("Invalid player index".to_string());
         }
+        // JSON clients send amounts unchecked
+        if let PlayerAction::Raise(amount) = action
+            && !(amount.is_finite() && amount > 0.0)
+        {
+            return Err("Bet amount must be positive".to_string());
+        }
         
-        let player = &mut self.players[player_index];
+        match (&action, &self.current_round) {
+            (_, BettingRound::ShowOrMuck) => return Err("Betting is over; show your hand with SHOW_HAND or MUCK it".to_string()),
+            (PlayerAction::Fold, _)
+            | (PlayerAction::Draw(_), BettingRound::Draw(_))
+            | (PlayerAction::Discard(_), BettingRound::Discard) => {}
+            (PlayerAction::Draw(_), _) => return Err("There is no draw right now".to_string()),
+            (PlayerAction::Discard(_), _) => return Err("There is no discard right now".to_string()),
+            (_, BettingRound::Draw(_)) => return Err("Betting is closed during the draw".to_string()),
+            (_, BettingRound::Discard) => return Err("Betting is closed during the discard".to_string()),
+            (_, BettingRound::RunoutVote) => return Err("Everyone is all-in; vote with RUN <times>".to_string()),
+            _ => {}
+        }
+        
+        // Calling or betting the whole stack (or more) puts the player all-in
+        let player = &self.players[player_index];
+        let to_call = (self.current_bet - player.chips_in_play).max(0.0);
+        let action = match action {
+            PlayerAction::Call if to_call > 0.0 && to_call >= player.balance => PlayerAction::AllIn,
+            PlayerAction::Raise(amount) if to_call + amount >= player.balance => PlayerAction::AllIn,
+            action => action,
+        };
+        
+        if let Some(size) = self.fixed_limit_bet() {
+            // An all-in counts as a raise by whatever it puts in over the current bet
+            let player = &self.players[player_index];
+            let raise = match action {
+                PlayerAction::Raise(amount) if amount != size => {
+                    return Err(format!("Bets and raises are {} on this street", size));
+                }
+                PlayerAction::Raise(amount) => amount,
+                PlayerAction::AllIn => player.chips_in_play + player.balance - self.current_bet,
+                _ => 0.0,
+            };
+            if raise > size {
+                return Err(format!("Bets and raises are {} on this street; you cannot go all-in for more", size));
+            }
+            if raise > 0.0 && self.bets_this_round >= FIXED_LIMIT_CAP {
+                return Err("Betting is capped for this round".to_string());
+            }
+        }
         
-        match action {
+        let player = &mut self.players[player_index];
+        let taken = match action {
             PlayerAction::Fold => {
                 player.fold();
                 self.update_active_players();
+                ActionTaken::Fold
             }
             PlayerAction::Check => {
                 if self.current_bet > player.chips_in_play {
                     return Err("Cannot check when there's a bet to call".to_string());
                 }
                 player.check();
+                ActionTaken::Check
             }
             PlayerAction::Call => {
                 let call_amount = self.current_bet - player.chips_in_play;
                 if call_amount > 0.0 {
                     player.call(call_amount)?;
                     self.pot += call_amount;
+                    ActionTaken::Call { amount: call_amount }
                 } else {
                     player.check();
+                    ActionTaken::Check
                 }
             }
             PlayerAction::Raise(amount) => {
-                let total_needed = self.current_bet + amount;
-                player.raise(amount)?;
-                self.pot += amount;
-                self.current_bet = total_needed;
+                // A raise puts in the amount to call plus the raise itself
+                let to_call = (self.current_bet - player.chips_in_play).max(0.0);
+                player.raise(to_call + amount)?;
+                self.pot += to_call + amount;
+                self.current_bet = player.chips_in_play;
+                self.bets_this_round += 1;
+                self.last_aggressor = Some(player_index);
                 self.reset_other_player_actions(player_index);
+                ActionTaken::Bet { amount, to: self.current_bet }
             }
             PlayerAction::AllIn => {
                 let amount = player.all_in();
                 self.pot += amount;
                 if player.chips_in_play > self.current_bet {
                     self.current_bet = player.chips_in_play;
+                    self.bets_this_round += 1;
+                    self.last_aggressor = Some(player_index);
                     self.reset_other_player_actions(player_index);
                 }
+                ActionTaken::AllIn { amount }
             }
-        }
+            PlayerAction::Draw(discards) => {
+                if player.action.is_some() {
+                    return Err("You have already drawn".to_string());
+                }
+                if discards.len() > player.hole_cards.len() {
+                    return Err("Too many cards to discard".to_string());
+                }
+                for (i, card) in discards.iter().enumerate() {
+                    if !player.hole_cards.contains(card) || discards[..i].contains(card) {
+                        return Err(format!("You do not hold {}", card));
+                    }
+                }
+                // Discards from earlier draws are reshuffled in when the deck runs short
+                if self.deck.remaining() < discards.len() {
+                    self.deck.reshuffle_discards();
+                }
+                if self.deck.remaining() < discards.len() {
+                    return Err(format!("Only {} cards are left to draw", self.deck.remaining()));
+                }
+                let new_cards: Vec<Card> = (0..discards.len()).filter_map(|_| self.deck.draw()).collect();
+                self.deck.discard(&discards);
+                self.history.record(HandEvent::Draw {
+                    player: player.name.clone(),
+                    discarded: discards.clone(),
+                    received: new_cards.clone(),
+                });
+                player.draw(&discards, new_cards);
+                ActionTaken::Draw { count: discards.len() }
+            }
+            PlayerAction::Discard(card) => {
+                if player.action.is_some() {
+                    return Err("You have already discarded".to_string());
+                }
+                if !player.hole_cards.contains(&card) {
+                    return Err(format!("You do not hold {}", card));
+                }
+                self.deck.discard(&[card]);
+                self.history.record(HandEvent::Discard { player: player.name.clone(), card });
+                player.discard(card);
+                ActionTaken::Discard
+            }
+        };
         
-        Ok(())
+        // Draws and discards have recorded their own events, with the cards
+        if !matches!(taken, ActionTaken::Draw { .. } | ActionTaken::Discard) {
+            let player = self.players[player_index].name.clone();
+            self.history.record(HandEvent::Action { player, action: taken.clone() });
+        }
+        self.actions_taken += 1;
+        Ok(taken)
     }
 
     fn reset_other_player_actions(&mut self, current_player: usize) {
@@ -235,21 +833,264 @@ impl Game {
     }
 
     pub fn next_player(&mut self) {
-        loop {
+        // All-in players cannot bet, but they still draw and discard
+        let drawing = !self.is_betting_phase();
+        // Stops after a full lap if nobody can act, e.g. when the antes put everyone all-in
+        for _ in 0..self.players.len() {
             self.current_player = (self.current_player + 1) % self.players.len();
-            if self.players[self.current_player].state == PlayerState::Active {
+            let state = &self.players[self.current_player].state;
+            let voted = self.run_votes.iter().any(|&(i, _)| i == self.current_player);
+            if (*state == PlayerState::Active || (drawing && *state == PlayerState::AllIn)) && !voted {
+                break;
+            }
+        }
+    }
+
+    /// Whether the current hand is a double-board bomb pot
+    pub fn is_double_board(&self) -> bool {
+        matches!(self.hand_start, HandStart::BombPot { double_board: true, .. })
+    }
+
+    /// Whether players are currently betting rather than drawing, discarding or voting on the runout
+    pub fn is_betting_phase(&self) -> bool {
+        !matches!(self.current_round, BettingRound::Draw(_) | BettingRound::Discard | BettingRound::RunoutVote | BettingRound::ShowOrMuck)
+    }
+
+    /// Whether nobody can bet any more: at least two players are still in the hand
+    /// and at most one of them has chips behind
+    pub fn is_all_in(&self) -> bool {
+        let with_chips = self.players.iter().filter(|p| p.state == PlayerState::Active).count();
+        self.live_player_count() > 1 && with_chips <= 1
+    }
+
+    /// Whether the rest of the board could be run more than once: the variant deals
+    /// a board and it is not complete yet
+    pub fn can_run_multiple(&self) -> bool {
+        let board_size: usize = self.variant.board_deals().iter().sum();
+        !self.is_double_board()
+            && matches!(self.current_round, BettingRound::PreFlop | BettingRound::Flop | BettingRound::Turn)
+            && self.community_cards.len() < board_size
+    }
+
+    /// Starts the runout vote once everyone is all-in; players vote in turn from the left of the button
+    pub fn start_runout_vote(&mut self) {
+        self.current_round = BettingRound::RunoutVote;
+        self.run_votes.clear();
+        self.current_player = self.dealer_position;
+        self.next_player();
+    }
+
+    /// Records a player's vote to run the rest of the board `runs` times
+    pub fn vote_runs(&mut self, player_index: usize, runs: usize) -> Result<(), String> {
+        if self.current_round != BettingRound::RunoutVote {
+            return Err("There is no runout vote right now".to_string());
+        }
+        if !(1..=MAX_RUNS).contains(&runs) {
+            return Err(format!("The board can be run 1 to {} times", MAX_RUNS));
+        }
+        let player = self.players.get(player_index).ok_or("Invalid player index")?;
+        if !matches!(player.state, PlayerState::Active | PlayerState::AllIn) {
+            return Err("You are not in this hand".to_string());
+        }
+        if self.run_votes.iter().any(|&(i, _)| i == player_index) {
+            return Err("You have already voted".to_string());
+        }
+        self.run_votes.push((player_index, runs));
+        self.actions_taken += 1;
+        Ok(())
+    }
+
+    /// Number of board streets (flop, turn, river) already dealt on a board of the given size
+    fn streets_dealt(&self, board_len: usize) -> usize {
+        let mut dealt = 0;
+        let mut streets = 0;
+        for &count in self.variant.board_deals() {
+            if dealt + count > board_len {
                 break;
             }
+            dealt += count;
+            streets += 1;
+        }
+        streets
+    }
+
+    /// Reveals the community cards the last hand would have dealt had it gone on,
+    /// burning a card before each street as the dealer would have. At most `limit`
+    /// reveals are allowed per hand; 0 turns rabbit hunting off.
+    pub fn rabbit_hunt(&mut self, limit: usize) -> Result<Vec<Card>, String> {
+        if limit == 0 {
+            return Err("Rabbit hunting is off at this table".to_string());
+        }
+        let streets_done = self.rabbit.as_ref().map(|r| self.streets_dealt(r.board.len())).unwrap_or(0);
+        let rabbit = self.rabbit.as_mut().ok_or("The last hand has no undealt board")?;
+        if rabbit.hunts >= limit {
+            return Err(format!("Rabbit hunting is limited to {} per hand", limit));
+        }
+        rabbit.hunts += 1;
+        let mut deck = rabbit.deck.clone();
+        let mut cards = Vec::new();
+        for &count in &self.variant.board_deals()[streets_done..] {
+            deck.draw();
+            for _ in 0..count {
+                if let Some(card) = deck.draw() {
+                    cards.push(card);
+                }
+            }
+        }
+        Ok(cards)
+    }
+
+    /// Number of runs agreed: the board runs more than once only if every player asked for the same number
+    pub fn agreed_runs(&self) -> usize {
+        match self.run_votes.first() {
+            Some(&(_, runs)) if self.run_votes.iter().all(|&(_, r)| r == runs) => runs,
+            _ => 1,
+        }
+    }
+
+    /// Deals the rest of the board once per agreed run, leaving the round at the
+    /// river. Each run gets its own cards; fewer runs are dealt if the deck is short.
+    pub fn run_out(&mut self) {
+        let streets_done = self.streets_dealt(self.community_cards.len());
+        let deals = self.variant.board_deals();
+        let remaining = &deals[streets_done..];
+        // Every street burns a card before it is dealt
+        let per_run: usize = remaining.iter().map(|n| n + 1).sum();
+        let runs = self.agreed_runs().min(self.deck.remaining() / per_run.max(1)).max(1);
+
+        if runs == 1 || self.is_double_board() {
+            for street in streets_done..deals.len() {
+                self.deal_board(street);
+            }
+        } else {
+            for run in 0..runs {
+                let mut board = self.community_cards.clone();
+                let mut cards = Vec::new();
+                for &count in remaining {
+                    self.deck.draw();
+                    for _ in 0..count {
+                        if let Some(card) = self.deck.draw() {
+                            board.push(card);
+                            cards.push(card);
+                        }
+                    }
+                }
+                self.history.record(HandEvent::Run { run: run + 1, cards });
+                self.boards.push(board);
+            }
+        }
+        self.current_round = BettingRound::River;
+    }
+
+    /// Starts the Pineapple discard: every player throws away one of their three
+    /// hole cards, in turn from the left of the button
+    pub fn start_discard(&mut self) {
+        self.current_round = BettingRound::Discard;
+        self.reset_player_actions();
+        self.current_player = self.dealer_position;
+        self.next_player();
+    }
+
+    /// Ends the discard and deals the street that follows it: the flop in
+    /// Pineapple, or the turn in Crazy Pineapple
+    pub fn finish_discard(&mut self) {
+        if self.community_cards.is_empty() {
+            self.current_round = BettingRound::PreFlop;
+            self.deal_flop();
+        } else {
+            self.current_round = BettingRound::Flop;
+            self.deal_turn();
         }
+        self.current_player = self.dealer_position;
+        self.next_player();
+    }
+
+    /// The card thrown away for a player who runs out of time: their lowest card
+    pub fn default_discard(&self, player_index: usize) -> Option<Card> {
+        self.players[player_index].hole_cards.iter().min().copied()
+    }
+
+    /// Acts for a player whose time ran out: checks if they can, folds otherwise.
+    /// In a draw they stand pat, in a discard they throw their lowest card, in a
+    /// runout vote they run the board once and at showdown their auto-muck setting
+    /// decides whether their losing hand is shown. Returns the action taken for them.
+    pub fn act_on_timeout(&mut self, player_index: usize) -> ActionTaken {
+        let acted = match self.current_round {
+            BettingRound::Discard => self.default_discard(player_index)
+                .ok_or_else(|| "No cards to discard".to_string())
+                .and_then(|card| self.player_action(player_index, PlayerAction::Discard(card))),
+            BettingRound::RunoutVote => self.vote_runs(player_index, 1).map(|_| ActionTaken::Vote { runs: 1 }),
+            BettingRound::ShowOrMuck => self.show_or_muck(player_index, !self.players[player_index].auto_muck),
+            BettingRound::Draw(_) => self.player_action(player_index, PlayerAction::Draw(Vec::new())),
+            _ if self.players[player_index].chips_in_play >= self.current_bet => {
+                self.player_action(player_index, PlayerAction::Check)
+            }
+            _ => Err(String::new()),
+        };
+        acted.unwrap_or_else(|_| {
+            let _ = self.player_action(player_index, PlayerAction::Fold);
+            ActionTaken::Fold
+        })
+    }
+
+    /// Identifies whose turn it is and how far the hand has got, so a turn can be
+    /// told apart from the next one even when the same player acts twice in a row
+    pub fn turn_key(&self) -> Option<(usize, usize, usize)> {
+        if !self.hand_in_progress || self.current_round == BettingRound::Showdown {
+            return None;
+        }
+        // All-in players still draw, discard and vote on the runout, but never bet
+        let player = self.players.get(self.current_player)?;
+        let can_act = match player.state {
+            PlayerState::Active => true,
+            PlayerState::AllIn => !self.is_betting_phase(),
+            _ => self.current_round == BettingRound::RunoutVote,
+        };
+        if !can_act {
+            return None;
+        }
+        Some((self.hands_dealt, self.actions_taken, self.current_player))
+    }
+
+    /// Moves a draw game to its next phase: from a betting round to the next draw,
+    /// or from a draw to the betting round after it. The first player left of the
+    /// button acts first in both.
+    pub fn next_draw_phase(&mut self) {
+        match self.current_round {
+            BettingRound::PreDraw => self.start_draw(1),
+            BettingRound::PostDraw(n) if n < self.variant.draw_rounds() => self.start_draw(n + 1),
+            BettingRound::Draw(n) => {
+                self.start_betting_round(BettingRound::PostDraw(n));
+                self.current_player = self.dealer_position;
+                self.next_player();
+            }
+            _ => {}
+        }
+    }
+
+    fn start_draw(&mut self, n: usize) {
+        self.current_round = BettingRound::Draw(n);
+        self.reset_player_actions();
+        self.current_player = self.dealer_position;
+        self.next_player();
     }
 
     pub fn is_betting_round_complete(&self) -> bool {
+        if self.current_round == BettingRound::RunoutVote {
+            return self.run_votes.len() >= self.live_player_count();
+        }
+        if !self.is_betting_phase() {
+            return self.get_active_players().iter()
+                .all(|p| matches!(p.action, Some(PlayerAction::Draw(_) | PlayerAction::Discard(_))));
+        }
+
         let active_players = self.players.iter()
             .filter(|p| p.state == PlayerState::Active)
             .collect::<Vec<_>>();
         
         if active_players.len() <= 1 {
-            return true;
+            // The last player with chips still has to answer an all-in bet above theirs
+            return active_players.iter().all(|p| p.chips_in_play >= self.current_bet);
         }
         
         // Check if all active players have acted and bets are equal
@@ -276,4 +1117,1118 @@ impl Game {
             .filter(|p| p.state == PlayerState::Active || p.state == PlayerState::AllIn)
             .collect()
     }
-}
\ No newline at end of file
+
+    /// Number of players still contesting the pot (not folded or sitting out)
+    pub fn live_player_count(&self) -> usize {
+        self.get_active_players().len()
+    }
+
+    /// Splits the chips committed this hand into a main pot and side pots.
+    /// Each pot lists the indices of the players eligible to win it.
+    pub fn build_pots(&self) -> Vec<(f64, Vec<usize>)> {
+        let is_live = |p: &Player| p.state == PlayerState::Active || p.state == PlayerState::AllIn;
+        let mut levels: Vec<f64> = self.players.iter()
+            .filter(|p| is_live(p))
+            .map(|p| p.chips_in_play)
+            .collect();
+        levels.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        levels.dedup();
+
+        let mut pots: Vec<(f64, Vec<usize>)> = Vec::new();
+        let mut previous = 0.0;
+        for &level in &levels {
+            let amount: f64 = self.players.iter()
+                .map(|p| p.chips_in_play.min(level) - p.chips_in_play.min(previous))
+                .sum();
+            let eligible: Vec<usize> = self.players.iter().enumerate()
+                .filter(|(_, p)| is_live(p) && p.chips_in_play >= level)
+                .map(|(i, _)| i)
+                .collect();
+            if amount > 0.0 {
+                pots.push((amount, eligible));
+            }
+            previous = level;
+        }
+        // Folded players may have put in more than any live player; it goes to the last pot
+        let dead_money: f64 = self.players.iter()
+            .map(|p| (p.chips_in_play - previous).max(0.0))
+            .sum();
+        if dead_money > 0.0
+            && let Some(last) = pots.last_mut()
+        {
+            last.0 += dead_money;
+        }
+        pots
+    }
+
+    /// Settles every pot: evaluates hands, pays the winners and ends the hand.
+    /// In split-pot variants each pot is halved between the best high and the best
+    /// qualifying low; the high hand scoops when no low qualifies. When the board was
+    /// run more than once, each pot is shared equally between the runs (odd cents to
+    /// the first run) and every run is evaluated on its own.
+    pub fn showdown(&mut self) -> Vec<PotResult> {
+        let results = self.pot_results();
+        let contested = self.live_player_count() > 1;
+        if contested {
+            self.reveal_hands(&results);
+        }
+        for result in &results {
+            for &(i, amount) in &result.payouts {
+                self.players[i].collect_winnings(amount);
+                self.history.record(HandEvent::Won {
+                    player: self.players[i].name.clone(),
+                    amount,
+                    hand: contested.then(|| self.describe_hand(i, result.run)),
+                });
+            }
+        }
+        self.last_hand = Some(std::mem::take(&mut self.history));
+        self.last_hole_cards = self.players.iter()
+            .filter(|p| !p.hole_cards.is_empty())
+            .map(|p| (p.name.clone(), p.hole_cards.clone()))
+            .collect();
+        self.shown_last_hand.clear();
+        self.hand_ended_at = Some(Instant::now());
+        let board_size: usize = self.variant.board_deals().iter().sum();
+        self.rabbit = (!self.is_double_board() && self.boards.is_empty() && self.community_cards.len() < board_size).then(|| Rabbit {
+            board: self.community_cards.clone(),
+            deck: self.deck.clone(),
+            hunts: 0,
+        });
+        for player in &mut self.players {
+            player.chips_in_play = 0.0;
+        }
+        self.pot = 0.0;
+        self.current_round = BettingRound::Showdown;
+        self.hand_in_progress = false;
+        results
+    }
+
+    /// Who wins each pot, and how much, without paying anyone
+    fn pot_results(&self) -> Vec<PotResult> {
+        let pots = self.build_pots();
+        let runs = self.boards.len().max(1);
+        let mut results = Vec::new();
+        for (pot, eligible) in pots {
+            let units = to_units(pot);
+            let share = units / runs as u64;
+            for run in 0..runs {
+                let units = if run == 0 { units - share * (runs as u64 - 1) } else { share };
+                let split = self.variant.pot_split();
+                let (high_winners, low_winners) = if eligible.len() == 1 {
+                    // Uncontested pots are not evaluated
+                    if split == PotSplit::Low { (vec![], eligible.clone()) } else { (eligible.clone(), vec![]) }
+                } else {
+                    match split {
+                        PotSplit::High => (self.best_high(&eligible, run), vec![]),
+                        PotSplit::HighLow => (self.best_high(&eligible, run), self.best_low(&eligible, run)),
+                        PotSplit::Low => (vec![], self.best_low(&eligible, run)),
+                    }
+                };
+                let mut payouts: Vec<(usize, f64)> = Vec::new();
+                if low_winners.is_empty() {
+                    pay_shares(&mut payouts, &high_winners, units);
+                } else if high_winners.is_empty() {
+                    pay_shares(&mut payouts, &low_winners, units);
+                } else {
+                    // The odd cent between the halves goes to the high hand
+                    let low_half = units / 2;
+                    pay_shares(&mut payouts, &high_winners, units - low_half);
+                    pay_shares(&mut payouts, &low_winners, low_half);
+                }
+                results.push(PotResult { amount: from_units(units), high_winners, low_winners, payouts, run });
+            }
+        }
+        results
+    }
+
+    /// Shows some or (with no cards given) all of a player's hole cards from the last
+    /// hand, within `SHOW_CARDS_WINDOW` of its end and only once. The cards are added
+    /// to that hand's history; returns the cards shown.
+    pub fn show_cards(&mut self, player: &str, cards: &[Card], now: Instant) -> Result<Vec<Card>, String> {
+        let ended = self.hand_ended_at.ok_or("No hand has finished yet")?;
+        if now.duration_since(ended) > SHOW_CARDS_WINDOW {
+            return Err("Too late to show cards from the last hand".to_string());
+        }
+        let (_, hole_cards) = self.last_hole_cards.iter()
+            .find(|(name, _)| name == player)
+            .ok_or("You were not dealt in to the last hand")?;
+        if self.shown_last_hand.iter().any(|name| name == player) {
+            return Err("You have already shown cards from the last hand".to_string());
+        }
+        if let Some(card) = cards.iter().find(|c| !hole_cards.contains(c)) {
+            return Err(format!("You did not hold {}", card));
+        }
+        let shown = if cards.is_empty() { hole_cards.clone() } else { cards.to_vec() };
+        self.shown_last_hand.push(player.to_string());
+        if let Some(history) = &mut self.last_hand {
+            history.record(HandEvent::Shows { player: player.to_string(), cards: shown.clone() });
+        }
+        Ok(shown)
+    }
+
+    /// Live players in showdown order: the last aggressor first, or the first player
+    /// left of the button when nobody bet on the last round, then clockwise
+    pub fn showdown_order(&self) -> Vec<usize> {
+        let n = self.players.len();
+        let first = self.last_aggressor.unwrap_or((self.dealer_position + 1) % n);
+        (0..n)
+            .map(|k| (first + k) % n)
+            .filter(|&i| matches!(self.players[i].state, PlayerState::Active | PlayerState::AllIn))
+            .collect()
+    }
+
+    /// Turns over the hands nobody chose to show or muck, in showdown order. Hands
+    /// that must be shown are; any other losing hand is mucked if its player auto-mucks.
+    fn reveal_hands(&mut self, results: &[PotResult]) {
+        for (n, i) in self.showdown_order().into_iter().enumerate() {
+            if !self.reveals.iter().any(|r| r.player == i) {
+                let show = self.must_show(n, i, results) || !self.players[i].auto_muck;
+                self.reveal(i, show);
+            }
+        }
+    }
+
+    /// Whether a hand has to be turned over at showdown: the first hand in showdown
+    /// order, every hand that wins a share of a pot, and every hand once someone is
+    /// all-in at a table that plays all-in hands face up
+    fn must_show(&self, order: usize, player: usize, results: &[PotResult]) -> bool {
+        let face_up = self.all_in_face_up && self.players.iter().any(|p| p.state == PlayerState::AllIn);
+        let wins = results.iter().any(|r| r.payouts.iter().any(|&(w, amount)| w == player && amount > 0.0));
+        order == 0 || face_up || wins
+    }
+
+    /// Shows or mucks a player's hand at showdown
+    fn reveal(&mut self, player_index: usize, show: bool) {
+        let player = &self.players[player_index];
+        let cards = show.then(|| player.all_cards());
+        match &cards {
+            Some(cards) => self.history.record(HandEvent::Shows { player: player.name.clone(), cards: cards.clone() }),
+            None => self.history.record(HandEvent::Mucks { player: player.name.clone() }),
+        }
+        self.reveals.push(Reveal { player: player_index, cards });
+    }
+
+    /// Starts turning hands over once the betting is done, if the pot is contested.
+    /// Returns whether a player now has to choose to show or muck; if not, the hand
+    /// can be settled.
+    pub fn start_show_or_muck(&mut self) -> bool {
+        if self.live_player_count() <= 1 {
+            return false;
+        }
+        self.current_round = BettingRound::ShowOrMuck;
+        self.next_show_or_muck()
+    }
+
+    /// Turns over the hands that must be shown, in showdown order, up to the next
+    /// player who may muck a losing hand, and makes it their turn. Returns false
+    /// once every hand has been shown or mucked.
+    pub fn next_show_or_muck(&mut self) -> bool {
+        let results = self.pot_results();
+        for (n, i) in self.showdown_order().into_iter().enumerate() {
+            if self.reveals.iter().any(|r| r.player == i) {
+                continue;
+            }
+            if !self.must_show(n, i, &results) {
+                self.current_player = i;
+                return true;
+            }
+            self.reveal(i, true);
+        }
+        false
+    }
+
+    /// Shows or mucks a losing hand at showdown, for the player whose turn it is to choose
+    pub fn show_or_muck(&mut self, player_index: usize, show: bool) -> Result<ActionTaken, String> {
+        if self.current_round != BettingRound::ShowOrMuck {
+            return Err("There is no showdown right now".to_string());
+        }
+        if self.reveals.iter().any(|r| r.player == player_index) {
+            return Err("Your hand is already shown or mucked".to_string());
+        }
+        self.reveal(player_index, show);
+        self.actions_taken += 1;
+        Ok(match &self.reveals.last().unwrap().cards {
+            Some(cards) => ActionTaken::Show { cards: cards.clone() },
+            None => ActionTaken::Muck,
+        })
+    }
+
+    /// The board used for the given run: the community cards unless the board was run more than once
+    pub fn board(&self, run: usize) -> &[Card] {
+        self.boards.get(run).unwrap_or(&self.community_cards)
+    }
+
+    /// Players tied for the best high hand on a run of the board, ordered clockwise from the left of the button
+    fn best_high(&self, eligible: &[usize], run: usize) -> Vec<usize> {
+        let hands: Vec<(usize, EvaluatedHand)> = eligible.iter()
+            .map(|&i| (i, self.variant.evaluate_high(&self.players[i].all_cards(), self.board(run))))
+            .collect();
+        let ranking = self.variant.ranking();
+        let best = hands.iter().map(|(_, h)| h).max_by(|a, b| ranking.compare(a, b)).cloned();
+        let winners = hands.iter()
+            .filter(|(_, h)| best.as_ref().is_some_and(|b| ranking.compare(h, b) == Ordering::Equal))
+            .map(|(i, _)| *i)
+            .collect();
+        self.order_from_button(winners)
+    }
+
+    /// Players tied for the best qualifying low hand on a run of the board, empty when no low qualifies
+    fn best_low(&self, eligible: &[usize], run: usize) -> Vec<usize> {
+        let hands: Vec<(usize, LowHand)> = eligible.iter()
+            .filter_map(|&i| {
+                self.variant.evaluate_low(&self.players[i].all_cards(), self.board(run))
+                    .map(|low| (i, low))
+            })
+            .collect();
+        let best = hands.iter().map(|(_, h)| h).min().cloned();
+        let winners = hands.iter()
+            .filter(|(_, h)| Some(h) == best.as_ref())
+            .map(|(i, _)| *i)
+            .collect();
+        self.order_from_button(winners)
+    }
+
+    /// Describes a player's showdown hand on a run of the board, e.g. `Full house` or `8-6-4-3-A`
+    pub fn describe_hand(&self, player_index: usize, run: usize) -> String {
+        let cards = self.players[player_index].all_cards();
+        let low = self.variant.evaluate_low(&cards, self.board(run));
+        if !self.variant.plays_high() {
+            return low.map(|l| l.to_string()).unwrap_or_default();
+        }
+        let high = self.variant.evaluate_high(&cards, self.board(run));
+        match low {
+            Some(low) => format!("{} / {}", high.rank, low),
+            None => high.rank.to_string(),
+        }
+    }
+
+    /// Orders player indices clockwise starting left of the dealer button
+    fn order_from_button(&self, mut indices: Vec<usize>) -> Vec<usize> {
+        let n = self.players.len();
+        let first = (self.dealer_position + 1) % n;
+        indices.sort_by_key(|&i| (i + n - first) % n);
+        indices
+    }
+}
+
+fn to_units(amount: f64) -> u64 {
+    (amount * UNITS_PER_CHIP).round() as u64
+}
+
+fn from_units(units: u64) -> f64 {
+    units as f64 / UNITS_PER_CHIP
+}
+
+/// Splits `units` cents evenly between `winners`.
+/// Odd cents go one at a time to the winners in the order given.
+fn pay_shares(payouts: &mut Vec<(usize, f64)>, winners: &[usize], units: u64) {
+    if winners.is_empty() {
+        return;
+    }
+    let count = winners.len() as u64;
+    let shares = (0..count).map(|i| from_units(units / count + u64::from(i < units % count)));
+    for (&winner, share) in winners.iter().zip(shares) {
+        match payouts.iter_mut().find(|(i, _)| *i == winner) {
+            Some(entry) => entry.1 += share,
+            None => payouts.push((winner, share)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::{Rank, Suit};
+    use crate::history::render;
+    use crate::variant::{FiveCardDraw, OmahaHiLo, Pineapple, Razz, SevenCardStud, TexasHoldem, TripleDraw};
+
+    fn card(rank: Rank, suit: Suit) -> Card {
+        Card { rank, suit }
+    }
+
+    /// Builds an Omaha Hi-Lo game at showdown with the given hole cards and contributions
+    fn omaha_showdown(hands: Vec<[Card; 4]>, contributions: &[f64], board: [Card; 5]) -> Game {
+        let mut game = Game::new(Arc::new(OmahaHiLo), 1.0, 2.0);
+        for (i, (hole, &chips)) in hands.into_iter().zip(contributions).enumerate() {
+            game.add_player(format!("p{}", i), 0.0);
+            game.players[i].hole_cards = hole.to_vec();
+            game.players[i].chips_in_play = chips;
+        }
+        game.pot = contributions.iter().sum();
+        game.community_cards = board.to_vec();
+        game.dealer_position = game.players.len() - 1;
+        game
+    }
+
+    fn low_board() -> [Card; 5] {
+        [
+            card(Rank::Three, Suit::Spades),
+            card(Rank::Four, Suit::Diamonds),
+            card(Rank::Eight, Suit::Clubs),
+            card(Rank::King, Suit::Spades),
+            card(Rank::King, Suit::Diamonds),
+        ]
+    }
+
+    #[test]
+    fn test_high_scoops_when_no_low_qualifies() {
+        let board = [
+            card(Rank::Nine, Suit::Spades),
+            card(Rank::Ten, Suit::Diamonds),
+            card(Rank::Jack, Suit::Clubs),
+            card(Rank::King, Suit::Spades),
+            card(Rank::Two, Suit::Diamonds),
+        ];
+        let mut game = omaha_showdown(vec![
+            [card(Rank::Ace, Suit::Hearts), card(Rank::Three, Suit::Hearts), card(Rank::King, Suit::Clubs), card(Rank::Five, Suit::Clubs)],
+            [card(Rank::Queen, Suit::Hearts), card(Rank::Eight, Suit::Hearts), card(Rank::Four, Suit::Clubs), card(Rank::Four, Suit::Hearts)],
+        ], &[10.0, 10.0], board);
+        let results = game.showdown();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].low_winners.is_empty());
+        assert_eq!(results[0].payouts, vec![(1, 20.0)]);
+        assert_eq!(game.players[1].balance, 20.0);
+    }
+
+    #[test]
+    fn test_pot_splits_high_and_low_with_odd_chip_to_high() {
+        let mut game = omaha_showdown(vec![
+            // Kings full for high, no low
+            [card(Rank::King, Suit::Hearts), card(Rank::Queen, Suit::Hearts), card(Rank::Queen, Suit::Clubs), card(Rank::Nine, Suit::Clubs)],
+            // 8-4-3-2-A low
+            [card(Rank::Ace, Suit::Hearts), card(Rank::Two, Suit::Hearts), card(Rank::Jack, Suit::Clubs), card(Rank::Ten, Suit::Hearts)],
+        ], &[10.0, 11.0], low_board());
+        // One chip of the second player is uncalled and forms its own pot
+        let results = game.showdown();
+        assert_eq!(results[0].amount, 20.0);
+        assert_eq!(results[0].high_winners, vec![0]);
+        assert_eq!(results[0].low_winners, vec![1]);
+        assert_eq!(game.players[0].balance, 10.0);
+        assert_eq!(game.players[1].balance, 11.0);
+
+        let mut game = omaha_showdown(vec![
+            [card(Rank::King, Suit::Hearts), card(Rank::Queen, Suit::Hearts), card(Rank::Queen, Suit::Clubs), card(Rank::Nine, Suit::Clubs)],
+            [card(Rank::Ace, Suit::Hearts), card(Rank::Two, Suit::Hearts), card(Rank::Jack, Suit::Clubs), card(Rank::Ten, Suit::Hearts)],
+            [card(Rank::Ace, Suit::Clubs), card(Rank::Queen, Suit::Diamonds), card(Rank::Jack, Suit::Diamonds), card(Rank::Six, Suit::Spades)],
+        ], &[7.01, 7.01, 7.01], low_board());
+        let results = game.showdown();
+        assert_eq!(results[0].amount, 21.03);
+        assert_eq!(game.players[0].balance, 10.52);
+        assert_eq!(game.players[1].balance, 10.51);
+    }
+
+    #[test]
+    fn test_fractional_pot_is_split_to_the_cent() {
+        let mut game = omaha_showdown(vec![
+            [card(Rank::King, Suit::Hearts), card(Rank::Queen, Suit::Hearts), card(Rank::Queen, Suit::Clubs), card(Rank::Nine, Suit::Clubs)],
+            [card(Rank::Ace, Suit::Hearts), card(Rank::Two, Suit::Hearts), card(Rank::Jack, Suit::Clubs), card(Rank::Ten, Suit::Hearts)],
+            [card(Rank::Ace, Suit::Clubs), card(Rank::Two, Suit::Clubs), card(Rank::Jack, Suit::Hearts), card(Rank::Ten, Suit::Spades)],
+        ], &[0.75, 0.75, 0.75], low_board());
+        let results = game.showdown();
+        assert_eq!(results[0].amount, 2.25);
+        assert_eq!(results[0].low_winners, vec![1, 2]);
+        // 1.13 to the high hand and 1.12 quartered between the tied lows
+        assert_eq!(game.players[0].balance, 1.13);
+        assert_eq!(game.players[1].balance, 0.56);
+        assert_eq!(game.players[2].balance, 0.56);
+    }
+
+    #[test]
+    fn test_tied_lows_are_quartered() {
+        let mut game = omaha_showdown(vec![
+            [card(Rank::King, Suit::Hearts), card(Rank::Queen, Suit::Hearts), card(Rank::Queen, Suit::Clubs), card(Rank::Nine, Suit::Clubs)],
+            [card(Rank::Ace, Suit::Hearts), card(Rank::Two, Suit::Hearts), card(Rank::Jack, Suit::Clubs), card(Rank::Ten, Suit::Hearts)],
+            [card(Rank::Ace, Suit::Clubs), card(Rank::Two, Suit::Clubs), card(Rank::Jack, Suit::Hearts), card(Rank::Ten, Suit::Spades)],
+        ], &[8.0, 8.0, 8.0], low_board());
+        let results = game.showdown();
+        assert_eq!(results[0].low_winners, vec![1, 2]);
+        assert_eq!(game.players[0].balance, 12.0);
+        assert_eq!(game.players[1].balance, 6.0);
+        assert_eq!(game.players[2].balance, 6.0);
+    }
+
+    #[test]
+    fn test_side_pot_excludes_short_all_in() {
+        let mut game = omaha_showdown(vec![
+            [card(Rank::King, Suit::Hearts), card(Rank::Queen, Suit::Hearts), card(Rank::Queen, Suit::Clubs), card(Rank::Nine, Suit::Clubs)],
+            [card(Rank::Ace, Suit::Hearts), card(Rank::Two, Suit::Hearts), card(Rank::Jack, Suit::Clubs), card(Rank::Ten, Suit::Hearts)],
+            [card(Rank::Seven, Suit::Clubs), card(Rank::Six, Suit::Clubs), card(Rank::Jack, Suit::Hearts), card(Rank::Ten, Suit::Spades)],
+        ], &[5.0, 20.0, 20.0], low_board());
+        game.players[0].state = PlayerState::AllIn;
+        let pots = game.build_pots();
+        assert_eq!(pots, vec![(15.0, vec![0, 1, 2]), (30.0, vec![1, 2])]);
+    }
+
+    #[test]
+    fn test_holdem_raises_and_calls_count_the_whole_hand() {
+        let mut game = Game::new(Arc::new(TexasHoldem), 1.0, 2.0);
+        for i in 0..3 {
+            game.add_player(format!("p{}", i), 100.0);
+        }
+        game.start_new_hand().unwrap();
+        // p2 posts the small blind, p0 the big blind and p1 acts first
+        assert_eq!(game.current_player, 1);
+        game.player_action(1, PlayerAction::Raise(4.0)).unwrap();
+        assert_eq!(game.players[1].chips_in_play, 6.0);
+        assert_eq!(game.current_bet, 6.0);
+        game.player_action(2, PlayerAction::Call).unwrap();
+        game.player_action(0, PlayerAction::Call).unwrap();
+        assert_eq!(game.pot, 18.0);
+        assert!(game.is_betting_round_complete());
+
+        // The preflop bets carry over, so nobody owes anything on the flop
+        game.deal_flop();
+        assert_eq!(game.current_bet, 6.0);
+        game.player_action(2, PlayerAction::Check).unwrap();
+        game.player_action(0, PlayerAction::Raise(10.0)).unwrap();
+        assert_eq!(game.current_bet, 16.0);
+        assert!(game.player_action(1, PlayerAction::Check).is_err());
+        game.player_action(1, PlayerAction::Call).unwrap();
+        assert_eq!(game.players[1].balance, 84.0);
+        assert_eq!(game.pot, 38.0);
+    }
+
+    fn stud_game(players: usize) -> Game {
+        let mut game = Game::new(Arc::new(SevenCardStud), 1.0, 2.0);
+        game.ante = 1.0;
+        for i in 0..players {
+            game.add_player(format!("p{}", i), 100.0);
+        }
+        game.start_new_hand().unwrap();
+        game
+    }
+
+    #[test]
+    fn test_stud_antes_that_leave_one_player_with_chips_deal_out_the_hand() {
+        let mut game = stud_game(3);
+        game.ante = 5.0;
+        for (player, stack) in game.players.iter_mut().zip([1.0, 2.0, 100.0]) {
+            player.balance = stack;
+        }
+        game.start_new_hand().unwrap();
+        assert_eq!(game.current_round, BettingRound::Showdown);
+        assert_eq!(game.turn_key(), None);
+        assert!(game.players.iter().all(|p| p.all_cards().len() == 7));
+        game.showdown();
+        assert!(!game.hand_in_progress);
+        assert_eq!(game.players.iter().map(|p| p.balance).sum::<f64>(), 103.0);
+    }
+
+    #[test]
+    fn test_stud_bring_in_goes_to_lowest_up_card() {
+        let game = stud_game(4);
+        assert_eq!(game.current_round, BettingRound::ThirdStreet);
+        assert_eq!(game.pot, 4.0 + game.bring_in);
+        let lowest = game.players.iter().map(|p| p.up_cards[0]).min().unwrap();
+        let bring_in = game.players.iter().position(|p| p.up_cards[0] == lowest).unwrap();
+        assert_eq!(game.players[bring_in].chips_in_play, 2.0);
+        assert_eq!(game.current_player, (bring_in + 1) % 4);
+        for player in &game.players {
+            assert_eq!(player.hole_cards.len(), 2);
+            assert_eq!(player.up_cards.len(), 1);
+        }
+    }
+
+    #[test]
+    fn test_stud_best_visible_hand_acts_first() {
+        let mut game = stud_game(3);
+        game.players[0].up_cards = vec![card(Rank::King, Suit::Clubs), card(Rank::Two, Suit::Clubs)];
+        game.players[1].up_cards = vec![card(Rank::Four, Suit::Hearts), card(Rank::Four, Suit::Spades)];
+        game.players[2].up_cards = vec![card(Rank::Ace, Suit::Hearts), card(Rank::Queen, Suit::Spades)];
+        assert_eq!(game.best_visible_hand(), Some(1));
+    }
+
+    #[test]
+    fn test_stud_deals_community_card_when_deck_runs_out() {
+        let mut game = stud_game(8);
+        for _ in 0..4 {
+            game.deal_stud_street();
+        }
+        assert_eq!(game.current_round, BettingRound::SeventhStreet);
+        assert_eq!(game.community_cards.len(), 1);
+        for player in &game.players {
+            assert_eq!(player.hole_cards.len(), 2);
+            assert_eq!(player.up_cards.len(), 4);
+        }
+    }
+
+    #[test]
+    fn test_razz_bring_in_goes_to_highest_up_card() {
+        let mut game = Game::new(Arc::new(Razz), 1.0, 2.0);
+        for i in 0..3 {
+            game.add_player(format!("p{}", i), 100.0);
+        }
+        game.start_new_hand().unwrap();
+        for player in &mut game.players {
+            player.reset_for_new_hand();
+        }
+        game.pot = 0.0;
+        game.players[0].up_cards = vec![card(Rank::Ace, Suit::Spades)];
+        game.players[1].up_cards = vec![card(Rank::King, Suit::Clubs)];
+        game.players[2].up_cards = vec![card(Rank::King, Suit::Spades)];
+        game.post_bring_in();
+        assert_eq!(game.players[2].chips_in_play, 1.0);
+        assert_eq!(game.current_player, 0);
+    }
+
+    #[test]
+    fn test_razz_best_low_scoops() {
+        let mut game = Game::new(Arc::new(Razz), 1.0, 2.0);
+        for i in 0..2 {
+            game.add_player(format!("p{}", i), 0.0);
+            game.players[i].chips_in_play = 10.0;
+        }
+        game.players[0].hole_cards = vec![card(Rank::Ace, Suit::Spades), card(Rank::Three, Suit::Hearts), card(Rank::King, Suit::Clubs)];
+        game.players[0].up_cards = vec![card(Rank::Four, Suit::Clubs), card(Rank::Six, Suit::Diamonds), card(Rank::Eight, Suit::Spades), card(Rank::King, Suit::Hearts)];
+        game.players[1].hole_cards = vec![card(Rank::Two, Suit::Spades), card(Rank::Three, Suit::Clubs), card(Rank::Four, Suit::Hearts)];
+        game.players[1].up_cards = vec![card(Rank::Five, Suit::Clubs), card(Rank::Nine, Suit::Diamonds), card(Rank::Queen, Suit::Spades), card(Rank::Jack, Suit::Hearts)];
+        assert_eq!(game.describe_hand(0, 0), "8-6-4-3-A");
+        let results = game.showdown();
+        assert!(results[0].high_winners.is_empty());
+        assert_eq!(results[0].low_winners, vec![0]);
+        assert_eq!(game.players[0].balance, 20.0);
+    }
+
+    #[test]
+    fn test_five_card_draw_replaces_discards() {
+        let mut game = Game::new(Arc::new(FiveCardDraw), 1.0, 2.0);
+        for i in 0..2 {
+            game.add_player(format!("p{}", i), 100.0);
+        }
+        game.start_new_hand().unwrap();
+        assert_eq!(game.current_round, BettingRound::PreDraw);
+        assert_eq!(game.players[0].hole_cards.len(), 5);
+        assert!(game.player_action(0, PlayerAction::Draw(vec![])).is_err());
+
+        game.next_draw_phase();
+        assert_eq!(game.current_round, BettingRound::Draw(1));
+        assert!(game.player_action(0, PlayerAction::Check).is_err());
+        let first = game.current_player;
+        let discards = game.players[first].hole_cards[..2].to_vec();
+        game.player_action(first, PlayerAction::Draw(discards.clone())).unwrap();
+        let hand = &game.players[first].hole_cards;
+        assert_eq!(hand.len(), 5);
+        assert!(discards.iter().all(|c| !hand.contains(c)));
+        assert!(game.player_action(first, PlayerAction::Draw(vec![])).is_err());
+
+        let other = 1 - first;
+        let foreign = game.players[first].hole_cards[0];
+        assert!(game.player_action(other, PlayerAction::Draw(vec![foreign])).is_err());
+        game.player_action(other, PlayerAction::Draw(vec![])).unwrap();
+        assert!(game.is_betting_round_complete());
+        game.next_draw_phase();
+        assert_eq!(game.current_round, BettingRound::PostDraw(1));
+    }
+
+    #[test]
+    fn test_draw_reshuffles_discards_when_deck_runs_short() {
+        let mut game = Game::new(Arc::new(FiveCardDraw), 1.0, 2.0);
+        for i in 0..2 {
+            game.add_player(format!("p{}", i), 100.0);
+        }
+        game.start_new_hand().unwrap();
+        game.next_draw_phase();
+        let first = game.current_player;
+        let mut thrown = Vec::new();
+        while game.deck.remaining() > 3 {
+            thrown.push(game.deck.draw().unwrap());
+        }
+        game.deck.discard(&thrown);
+        let discards = game.players[first].hole_cards.clone();
+        game.player_action(first, PlayerAction::Draw(discards.clone())).unwrap();
+        let hand = &game.players[first].hole_cards;
+        assert_eq!(hand.len(), 5);
+        assert!(discards.iter().all(|c| !hand.contains(c)));
+    }
+
+    #[test]
+    fn test_draw_fails_when_deck_and_discards_run_out() {
+        let mut game = Game::new(Arc::new(FiveCardDraw), 1.0, 2.0);
+        for i in 0..2 {
+            game.add_player(format!("p{}", i), 100.0);
+        }
+        game.start_new_hand().unwrap();
+        game.next_draw_phase();
+        let first = game.current_player;
+        while game.deck.remaining() > 2 {
+            game.deck.draw();
+        }
+        let hand = game.players[first].hole_cards.clone();
+        let err = game.player_action(first, PlayerAction::Draw(hand[..3].to_vec())).unwrap_err();
+        assert_eq!(err, "Only 2 cards are left to draw");
+        assert_eq!(game.players[first].hole_cards, hand);
+        game.player_action(first, PlayerAction::Draw(hand[..2].to_vec())).unwrap();
+        assert_eq!(game.players[first].hole_cards.len(), 5);
+    }
+
+    #[test]
+    fn test_triple_draw_fixed_limit_sizes_and_cap() {
+        let mut game = Game::new(Arc::new(TripleDraw), 1.0, 2.0);
+        for i in 0..3 {
+            game.add_player(format!("p{}", i), 100.0);
+        }
+        game.start_new_hand().unwrap();
+        assert_eq!(game.fixed_limit_bet(), Some(2.0));
+        assert!(game.player_action(0, PlayerAction::Raise(5.0)).is_err());
+        game.player_action(0, PlayerAction::Raise(2.0)).unwrap();
+        game.player_action(1, PlayerAction::Raise(2.0)).unwrap();
+        game.player_action(2, PlayerAction::Raise(2.0)).unwrap();
+        assert_eq!(game.current_bet, 8.0);
+        assert!(game.player_action(0, PlayerAction::Raise(2.0)).is_err());
+
+        game.current_round = BettingRound::PostDraw(2);
+        assert_eq!(game.fixed_limit_bet(), Some(4.0));
+    }
+
+    #[test]
+    fn test_fixed_limit_all_in_is_sized_and_capped() {
+        let mut game = Game::new(Arc::new(TripleDraw), 1.0, 2.0);
+        for i in 0..4 {
+            game.add_player(format!("p{}", i), 100.0);
+        }
+        game.start_new_hand().unwrap();
+        // An all-in may raise by no more than the bet size
+        assert!(game.player_action(0, PlayerAction::AllIn).is_err());
+        game.players[0].balance = game.current_bet - game.players[0].chips_in_play + 1.0;
+        game.player_action(0, PlayerAction::AllIn).unwrap();
+        assert_eq!(game.current_bet, 3.0);
+        game.player_action(1, PlayerAction::Raise(2.0)).unwrap();
+        game.player_action(2, PlayerAction::Raise(2.0)).unwrap();
+        // The short all-in was the second bet, so the round is now capped
+        game.players[3].balance = 6.0;
+        assert_eq!(game.player_action(3, PlayerAction::AllIn).unwrap_err(), "Betting is capped for this round");
+        game.players[3].balance = 5.0;
+        game.player_action(3, PlayerAction::AllIn).unwrap();
+        assert_eq!(game.current_bet, 7.0);
+    }
+
+    #[test]
+    fn test_pineapple_discard_before_flop() {
+        let mut game = Game::new(Arc::new(Pineapple), 1.0, 2.0);
+        for i in 0..2 {
+            game.add_player(format!("p{}", i), 100.0);
+        }
+        game.start_new_hand().unwrap();
+        assert_eq!(game.players[0].hole_cards.len(), 3);
+
+        game.start_discard();
+        assert_eq!(game.current_round, BettingRound::Discard);
+        assert!(game.player_action(game.current_player, PlayerAction::Check).is_err());
+        for _ in 0..2 {
+            let idx = game.current_player;
+            let card = game.default_discard(idx).unwrap();
+            game.player_action(idx, PlayerAction::Discard(card)).unwrap();
+            assert_eq!(game.players[idx].hole_cards.len(), 2);
+            assert!(!game.players[idx].hole_cards.contains(&card));
+            game.next_player();
+        }
+        assert!(game.is_betting_round_complete());
+        game.finish_discard();
+        assert_eq!(game.current_round, BettingRound::Flop);
+        assert_eq!(game.community_cards.len(), 3);
+    }
+
+    #[test]
+    fn test_history_hides_other_players_discards() {
+        let mut game = Game::new(Arc::new(Pineapple), 1.0, 2.0);
+        for i in 0..2 {
+            game.add_player(format!("p{}", i), 100.0);
+        }
+        game.start_new_hand().unwrap();
+        game.start_discard();
+        let idx = game.players.iter().position(|p| p.name == "p0").unwrap();
+        let card = game.players[idx].hole_cards[0];
+        game.player_action(idx, PlayerAction::Discard(card)).unwrap();
+        game.player_action(1 - idx, PlayerAction::Fold).unwrap();
+        game.showdown();
+
+        let history = game.last_hand.as_ref().unwrap();
+        let own = render(&history.view("p0"));
+        let other = render(&history.view("p1"));
+        assert!(own.contains(&format!("p0 discards {}", card)));
+        assert!(other.contains("p0 discards a card"));
+        assert!(!other.contains(&card.to_string()));
+        assert!(other.contains("p0 wins"));
+    }
+
+    #[test]
+    fn test_betting_or_calling_the_whole_stack_goes_all_in() {
+        let mut game = Game::new(Arc::new(TexasHoldem), 1.0, 2.0);
+        for i in 0..2 {
+            game.add_player(format!("p{}", i), 100.0);
+        }
+        game.start_new_hand().unwrap();
+        let first = game.current_player;
+        let other = 1 - first;
+        game.player_action(first, PlayerAction::Raise(500.0)).unwrap();
+        assert_eq!(game.players[first].state, PlayerState::AllIn);
+        assert_eq!(game.players[first].balance, 0.0);
+        // The other player still has to answer the shove
+        assert!(!game.is_betting_round_complete());
+        game.player_action(other, PlayerAction::Call).unwrap();
+        assert_eq!(game.players[other].state, PlayerState::AllIn);
+        assert!(game.is_all_in() && game.is_betting_round_complete());
+        assert!(matches!(
+            game.history.events.last(),
+            Some(HandEvent::Action { action: ActionTaken::AllIn { .. }, .. })
+        ));
+    }
+
+    #[test]
+    fn test_history_leaves_out_rejected_actions() {
+        let mut game = Game::new(Arc::new(TexasHoldem), 1.0, 2.0);
+        for i in 0..2 {
+            game.add_player(format!("p{}", i), 100.0);
+        }
+        game.start_new_hand().unwrap();
+        let events = game.history.events.len();
+        let first = game.current_player;
+        assert!(game.player_action(first, PlayerAction::Check).is_err());
+        for bad in [-5.0, 0.0, f64::NAN, f64::INFINITY] {
+            assert_eq!(game.player_action(first, PlayerAction::Raise(bad)).unwrap_err(), "Bet amount must be positive");
+        }
+        assert_eq!(game.current_bet, 2.0);
+        assert_eq!(game.history.events.len(), events);
+        game.player_action(first, PlayerAction::Call).unwrap();
+        assert!(matches!(
+            game.history.events.last(),
+            Some(HandEvent::Action { action: ActionTaken::Call { .. }, .. })
+        ));
+    }
+
+    /// Two players all-in before the flop with 100 chips each in the pot
+    fn all_in_preflop() -> Game {
+        let mut game = Game::new(Arc::new(TexasHoldem), 1.0, 2.0);
+        for i in 0..2 {
+            game.add_player(format!("p{}", i), 100.0);
+        }
+        game.start_new_hand().unwrap();
+        for i in 0..2 {
+            game.player_action(i, PlayerAction::AllIn).unwrap();
+        }
+        game
+    }
+
+    #[test]
+    fn test_run_it_twice_splits_each_pot_across_boards() {
+        let mut game = all_in_preflop();
+        assert!(game.is_all_in() && game.can_run_multiple());
+        game.start_runout_vote();
+        assert!(game.player_action(0, PlayerAction::Check).is_err());
+        assert!(game.vote_runs(0, 4).is_err());
+        game.vote_runs(0, 2).unwrap();
+        assert!(game.vote_runs(0, 2).is_err());
+        assert!(!game.is_betting_round_complete());
+        game.vote_runs(1, 2).unwrap();
+        assert!(game.is_betting_round_complete());
+
+        game.run_out();
+        assert_eq!(game.boards.len(), 2);
+        assert!(game.boards.iter().all(|b| b.len() == 5));
+        assert!(game.boards[0].iter().all(|c| !game.boards[1].contains(c)));
+        let results = game.showdown();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results.iter().map(|r| r.run).collect::<Vec<_>>(), vec![0, 1]);
+        assert!(results.iter().all(|r| r.amount == 100.0));
+        let total: f64 = game.players.iter().map(|p| p.balance).sum();
+        assert_eq!(total, 200.0);
+        let runs = render(&game.last_hand.as_ref().unwrap().view("p0"));
+        assert!(runs.contains("Run 1: ") && runs.contains("Run 2: "));
+    }
+
+    #[test]
+    fn test_runout_runs_once_without_agreement() {
+        let mut game = all_in_preflop();
+        game.start_runout_vote();
+        game.vote_runs(0, 3).unwrap();
+        game.vote_runs(1, 2).unwrap();
+        assert_eq!(game.agreed_runs(), 1);
+        game.run_out();
+        assert!(game.boards.is_empty());
+        assert_eq!(game.community_cards.len(), 5);
+        assert_eq!(game.current_round, BettingRound::River);
+    }
+
+    #[test]
+    fn test_rabbit_hunt_reveals_undealt_board_with_burns() {
+        let mut game = Game::new(Arc::new(TexasHoldem), 1.0, 2.0);
+        for i in 0..2 {
+            game.add_player(format!("p{}", i), 100.0);
+        }
+        game.start_new_hand().unwrap();
+        let mut deck = game.deck.clone();
+        game.player_action(0, PlayerAction::Fold).unwrap();
+        game.showdown();
+        let balances: Vec<f64> = game.players.iter().map(|p| p.balance).collect();
+
+        let mut expected = Vec::new();
+        for count in [3, 1, 1] {
+            deck.draw();
+            for _ in 0..count {
+                expected.push(deck.draw().unwrap());
+            }
+        }
+        assert!(game.rabbit_hunt(0).is_err());
+        assert_eq!(game.rabbit_hunt(1).unwrap(), expected);
+        assert!(game.rabbit_hunt(1).is_err());
+        assert_eq!(game.rabbit_hunt(2).unwrap(), expected);
+        assert_eq!(game.players.iter().map(|p| p.balance).collect::<Vec<_>>(), balances);
+        game.start_new_hand().unwrap();
+        assert!(game.rabbit_hunt(2).is_err());
+    }
+
+    fn holdem_game(players: usize) -> Game {
+        let mut game = Game::new(Arc::new(TexasHoldem), 1.0, 2.0);
+        for i in 0..players {
+            game.add_player(format!("p{}", i), 100.0);
+        }
+        game
+    }
+
+    #[test]
+    fn test_called_bomb_pot_starts_on_the_flop() {
+        let mut game = holdem_game(3);
+        game.call_bomb_pot(false).unwrap();
+        game.start_new_hand().unwrap();
+        assert_eq!(game.hand_start, HandStart::BombPot { ante: 5.0, double_board: false });
+        assert_eq!(game.current_round, BettingRound::Flop);
+        assert_eq!(game.community_cards.len(), 3);
+        assert_eq!(game.pot, 15.0);
+        assert!(game.players.iter().all(|p| p.chips_in_play == 5.0));
+        assert_eq!(game.current_player, (game.dealer_position + 1) % 3);
+        game.player_action(game.current_player, PlayerAction::Check).unwrap();
+
+        // The call only covers one hand
+        game.start_new_hand().unwrap();
+        assert_eq!(game.hand_start, HandStart::Normal);
+        assert_eq!(game.current_round, BettingRound::PreFlop);
+    }
+
+    #[test]
+    fn test_double_board_bomb_pot_splits_each_pot() {
+        let mut game = holdem_game(2);
+        game.bomb_pots = BombPotRules { every: 2, ante: 10.0, double_board: true };
+        game.start_new_hand().unwrap();
+        assert_eq!(game.hand_start, HandStart::Normal);
+        game.start_new_hand().unwrap();
+        assert!(game.is_double_board());
+        assert_eq!(game.boards.len(), 2);
+        assert!(game.boards.iter().all(|b| b.len() == 3));
+        assert!(game.boards[0].iter().all(|c| !game.boards[1].contains(c)));
+        assert_eq!(game.community_cards, game.boards[0]);
+        assert!(!game.can_run_multiple());
+
+        game.current_round = BettingRound::Flop;
+        game.deal_turn();
+        assert!(game.boards.iter().all(|b| b.len() == 4));
+        let results = game.showdown();
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|r| r.amount == 10.0));
+    }
+
+    #[test]
+    fn test_short_bomb_pot_ante_is_all_in() {
+        let mut game = holdem_game(3);
+        game.players[0].balance = 3.0;
+        game.call_bomb_pot(false).unwrap();
+        game.start_new_hand().unwrap();
+        assert_eq!(game.players[0].chips_in_play, 3.0);
+        assert_eq!(game.players[0].state, PlayerState::AllIn);
+        assert_eq!(game.pot, 13.0);
+        // The all-in player is skipped when the flop betting starts
+        assert_ne!(game.current_player, 0);
+        assert!(game.players[1..].iter().all(|p| p.state == PlayerState::Active));
+    }
+
+    #[test]
+    fn test_bomb_pot_with_every_stack_under_the_ante_is_dealt_out() {
+        let mut game = holdem_game(3);
+        for (player, stack) in game.players.iter_mut().zip([1.0, 2.0, 3.0]) {
+            player.balance = stack;
+        }
+        game.call_bomb_pot(true).unwrap();
+        game.start_new_hand().unwrap();
+        assert!(game.players.iter().all(|p| p.state == PlayerState::AllIn));
+        assert_eq!(game.current_round, BettingRound::Showdown);
+        assert!(game.boards.iter().all(|b| b.len() == 5));
+        assert_eq!(game.turn_key(), None);
+        game.showdown();
+        assert!(!game.hand_in_progress);
+        assert_eq!(game.players.iter().map(|p| p.balance).sum::<f64>(), 6.0);
+    }
+
+    #[test]
+    fn test_bomb_pot_needs_a_flop() {
+        let mut game = stud_game(2);
+        assert!(game.call_bomb_pot(false).is_err());
+    }
+
+    /// Three-handed Hold'em river where p1 holds the winning pair of aces
+    fn holdem_river() -> Game {
+        let mut game = holdem_game(3);
+        let hands = [
+            [card(Rank::Two, Suit::Clubs), card(Rank::Seven, Suit::Diamonds)],
+            [card(Rank::Ace, Suit::Clubs), card(Rank::Ace, Suit::Diamonds)],
+            [card(Rank::Three, Suit::Clubs), card(Rank::Eight, Suit::Diamonds)],
+        ];
+        for (player, hole) in game.players.iter_mut().zip(hands) {
+            player.hole_cards = hole.to_vec();
+            player.chips_in_play = 10.0;
+        }
+        game.pot = 30.0;
+        game.community_cards = vec![
+            card(Rank::King, Suit::Spades),
+            card(Rank::Queen, Suit::Hearts),
+            card(Rank::Nine, Suit::Spades),
+            card(Rank::Five, Suit::Hearts),
+            card(Rank::Four, Suit::Clubs),
+        ];
+        game.dealer_position = 2;
+        game
+    }
+
+    fn shown(game: &Game) -> Vec<(usize, bool)> {
+        game.reveals.iter().map(|r| (r.player, r.cards.is_some())).collect()
+    }
+
+    #[test]
+    fn test_last_aggressor_shows_first_and_losers_muck() {
+        let mut game = holdem_river();
+        game.last_aggressor = Some(2);
+        game.showdown();
+        assert_eq!(shown(&game), vec![(2, true), (0, false), (1, true)]);
+        let history = render(&game.last_hand.as_ref().unwrap().view("p2"));
+        assert!(history.contains("p0 mucks"));
+        assert!(history.contains("p1 shows Ac Ad"));
+
+        // Without an aggressor the first player left of the button shows first
+        let mut game = holdem_river();
+        game.players[2].auto_muck = false;
+        game.showdown();
+        assert_eq!(shown(&game), vec![(0, true), (1, true), (2, true)]);
+    }
+
+    #[test]
+    fn test_losing_players_choose_to_show_or_muck_in_turn() {
+        let mut game = holdem_river();
+        game.hand_in_progress = true;
+        game.last_aggressor = Some(2);
+        // p2 shows first, so p0 is the first with a losing hand to decide on
+        assert!(game.start_show_or_muck());
+        assert_eq!(shown(&game), vec![(2, true)]);
+        assert_eq!(game.current_player, 0);
+        assert!(game.turn_key().is_some());
+        assert!(game.player_action(0, PlayerAction::Fold).is_err());
+        assert_eq!(game.show_or_muck(0, true).unwrap(), ActionTaken::Show { cards: game.players[0].all_cards() });
+        assert!(game.show_or_muck(0, false).is_err());
+        // p1 wins, so their hand is turned over without asking
+        assert!(!game.next_show_or_muck());
+        assert_eq!(shown(&game), vec![(2, true), (0, true), (1, true)]);
+        game.showdown();
+        assert_eq!(shown(&game), vec![(2, true), (0, true), (1, true)]);
+        assert!(render(&game.last_hand.as_ref().unwrap().view("p2")).contains("p0 shows 2c 7d"));
+    }
+
+    #[test]
+    fn test_timeout_at_showdown_follows_the_auto_muck_setting() {
+        let mut game = holdem_river();
+        game.last_aggressor = Some(2);
+        assert!(game.start_show_or_muck());
+        assert_eq!(game.act_on_timeout(0), ActionTaken::Muck);
+
+        let mut game = holdem_river();
+        game.last_aggressor = Some(2);
+        game.players[0].auto_muck = false;
+        assert!(game.start_show_or_muck());
+        assert!(matches!(game.act_on_timeout(0), ActionTaken::Show { .. }));
+    }
+
+    #[test]
+    fn test_all_in_hands_are_face_up_when_the_table_says_so() {
+        let mut game = holdem_river();
+        game.last_aggressor = Some(1);
+        game.players[0].state = PlayerState::AllIn;
+        game.all_in_face_up = true;
+        game.showdown();
+        assert_eq!(shown(&game), vec![(1, true), (2, true), (0, true)]);
+    }
+
+    #[test]
+    fn test_show_cards_after_folding_within_the_window() {
+        let mut game = holdem_river();
+        game.players[0].state = PlayerState::Folded;
+        game.players[2].state = PlayerState::Folded;
+        game.showdown();
+        let ended = game.hand_ended_at.unwrap();
+
+        let deuce = card(Rank::Two, Suit::Clubs);
+        assert!(game.show_cards("p0", &[card(Rank::Ace, Suit::Clubs)], ended).is_err());
+        assert_eq!(game.show_cards("p0", &[deuce], ended).unwrap(), vec![deuce]);
+        assert_eq!(game.show_cards("p1", &[], ended).unwrap().len(), 2);
+        // Each player shows once per hand
+        assert!(game.show_cards("p0", &[], ended).is_err());
+        assert!(game.show_cards("p9", &[], ended).is_err());
+        assert!(game.show_cards("p2", &[], ended + SHOW_CARDS_WINDOW + Duration::from_secs(1)).is_err());
+        assert!(render(&game.last_hand.as_ref().unwrap().view("p2")).contains("p0 shows 2c"));
+    }
+
+    #[test]
+    fn test_timeout_checks_when_it_can_and_folds_otherwise() {
+        let mut game = holdem_game(3);
+        game.start_new_hand().unwrap();
+        let first = game.current_player;
+        assert_eq!(game.act_on_timeout(first), ActionTaken::Fold);
+        assert_eq!(game.players[first].state, PlayerState::Folded);
+        game.next_player();
+        game.player_action(game.current_player, PlayerAction::Call).unwrap();
+        game.next_player();
+        // The big blind has nothing to call, so their option is checked
+        let big_blind = game.current_player;
+        assert_eq!(game.act_on_timeout(big_blind), ActionTaken::Check);
+        assert_eq!(game.players[big_blind].state, PlayerState::Active);
+    }
+
+    #[test]
+    fn test_time_bank_refills_each_hand_up_to_the_cap() {
+        let mut game = Game::new(Arc::new(TexasHoldem), 1.0, 2.0);
+        game.clock = ClockRules { action_secs: 15, time_bank_secs: 10, refill_secs: 3, ..ClockRules::default() };
+        for i in 0..2 {
+            game.add_player(format!("p{}", i), 100.0);
+        }
+        assert!(game.players.iter().all(|p| p.time_bank == 10));
+        game.players[0].time_bank = 0;
+        game.start_new_hand().unwrap();
+        assert_eq!(game.players[0].time_bank, 3);
+        assert_eq!(game.players[1].time_bank, 10);
+        assert!(game.turn_key().is_some());
+    }
+
+    #[test]
+    fn test_disconnected_player_sits_out_after_missed_hands() {
+        let mut game = holdem_game(3);
+        game.clock.sit_out_after_hands = 1;
+        game.start_new_hand().unwrap();
+        game.set_disconnected("p1", true);
+        // Dealt one more hand, then left out of the next
+        game.start_new_hand().unwrap();
+        assert_eq!(game.players[1].hole_cards.len(), 2);
+        game.start_new_hand().unwrap();
+        assert!(game.players[1].sitting_out);
+        assert_eq!(game.players[1].state, PlayerState::SittingOut);
+        assert!(game.players[1].hole_cards.is_empty());
+        assert_eq!(game.players[1].chips_in_play, 0.0);
+        let posted = game.players.iter().filter(|p| p.chips_in_play > 0.0).count();
+        assert_eq!(posted, 2);
+        assert_ne!(game.current_player, 1);
+        // Two players are still enough, but not one
+        game.set_disconnected("p2", true);
+        game.players[2].missed_hands = 1;
+        assert!(game.start_new_hand().is_err());
+        // Coming back deals the player in again
+        game.set_disconnected("p1", false);
+        game.start_new_hand().unwrap();
+        assert_eq!(game.players[1].state, PlayerState::Active);
+        assert_eq!(game.players[1].hole_cards.len(), 2);
+    }
+}
@@ -1,6 +1,7 @@
-use crate::card::{Card, Rank};
+use crate::card::{Card, Rank, Suit};
 use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::fmt;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum HandRank {
@@ -14,29 +15,100 @@ pub enum HandRank {
     FourOfAKind,
     StraightFlush,
     RoyalFlush,
+    /// Only possible with wild cards
+    FiveOfAKind,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+impl fmt::Display for HandRank {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            HandRank::HighCard => "High card",
+            HandRank::OnePair => "One pair",
+            HandRank::TwoPair => "Two pair",
+            HandRank::ThreeOfAKind => "Three of a kind",
+            HandRank::Straight => "Straight",
+            HandRank::Flush => "Flush",
+            HandRank::FullHouse => "Full house",
+            HandRank::FourOfAKind => "Four of a kind",
+            HandRank::StraightFlush => "Straight flush",
+            HandRank::RoyalFlush => "Royal flush",
+            HandRank::FiveOfAKind => "Five of a kind",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct EvaluatedHand {
     pub rank: HandRank,
     pub cards: Vec<Card>, // 5 cards that make up the combination
 }
 
-impl Ord for EvaluatedHand {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.rank.cmp(&other.rank)
-            .then_with(|| self.cards[..].cmp(&other.cards[..]))
+impl EvaluatedHand {
+    /// Ranks of the 5 cards in the order they are compared (suits never break ties)
+    pub fn ranks(&self) -> Vec<Rank> {
+        self.cards.iter().map(|c| c.rank).collect()
     }
 }
 
-impl PartialOrd for EvaluatedHand {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
+impl PartialEq for EvaluatedHand {
+    /// Same category and the same ranks, which makes the hands tie under any ranking
+    fn eq(&self, other: &Self) -> bool {
+        self.rank == other.rank && self.ranks() == other.ranks()
+    }
+}
+
+impl Eq for EvaluatedHand {}
+
+/// Ranking rules for high hands. Short-deck reorders some categories and
+/// plays A-6-7-8-9 as the lowest straight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HandRanking {
+    #[default]
+    Standard,
+    ShortDeck {
+        /// Whether three of a kind beats a straight (it loses to one otherwise)
+        trips_beat_straight: bool,
+    },
+    /// Standard categories, but the ace is always high: A-2-3-4-5 is not a straight
+    DeuceToSeven,
+}
+
+impl HandRanking {
+    /// Relative strength of a hand category under these rules
+    fn category_strength(&self, rank: HandRank) -> u8 {
+        match (self, rank) {
+            (HandRanking::ShortDeck { .. }, HandRank::FullHouse) => HandRank::Flush as u8,
+            (HandRanking::ShortDeck { .. }, HandRank::Flush) => HandRank::FullHouse as u8,
+            (HandRanking::ShortDeck { trips_beat_straight: true }, HandRank::ThreeOfAKind) => HandRank::Straight as u8,
+            (HandRanking::ShortDeck { trips_beat_straight: true }, HandRank::Straight) => HandRank::ThreeOfAKind as u8,
+            _ => rank as u8,
+        }
+    }
+
+    /// Compares two evaluated hands under these rules
+    pub fn compare(&self, a: &EvaluatedHand, b: &EvaluatedHand) -> Ordering {
+        self.category_strength(a.rank).cmp(&self.category_strength(b.rank))
+            .then_with(|| a.ranks().cmp(&b.ranks()))
+    }
+
+    /// The four cards that make the lowest straight together with an ace
+    fn wheel_ranks(&self) -> Option<[Rank; 4]> {
+        match self {
+            HandRanking::Standard => Some([Rank::Two, Rank::Three, Rank::Four, Rank::Five]),
+            HandRanking::ShortDeck { .. } => Some([Rank::Six, Rank::Seven, Rank::Eight, Rank::Nine]),
+            HandRanking::DeuceToSeven => None,
+        }
     }
 }
 
 /// Evaluates the best poker hand from 7 cards
 pub fn evaluate_hand(cards: &[Card]) -> EvaluatedHand {
+    evaluate_hand_with(cards, HandRanking::Standard)
+}
+
+/// Evaluates the best poker hand from 7 cards under the given ranking rules
+pub fn evaluate_hand_with(cards: &[Card], ranking: HandRanking) -> EvaluatedHand {
     let mut best = EvaluatedHand {
         rank: HandRank::HighCard,
         cards: vec![],
@@ -52,8 +124,8 @@ pub fn evaluate_hand(cards: &[Card]) -> EvaluatedHand {
             cards[indices[3]],
             cards[indices[4]],
         ];
-        let eval = evaluate_five(&hand);
-        if eval > best {
+        let eval = evaluate_five(&hand, ranking);
+        if ranking.compare(&eval, &best) == Ordering::Greater {
             best = eval;
         }
         // Next combination
@@ -72,18 +144,295 @@ pub fn evaluate_hand(cards: &[Card]) -> EvaluatedHand {
     best
 }
 
-fn evaluate_five(cards: &[Card]) -> EvaluatedHand {
+/// Which cards play as wild: jokers, a declared rank (e.g. deuces wild), or both
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Wilds {
+    pub jokers: bool,
+    pub rank: Option<Rank>,
+}
+
+impl Wilds {
+    pub fn is_wild(&self, card: &Card) -> bool {
+        (self.jokers && card.rank == Rank::Joker) || self.rank == Some(card.rank)
+    }
+}
+
+/// Ranks a wild card can stand in for
+const NATURAL_RANKS: [Rank; 13] = [
+    Rank::Two,
+    Rank::Three,
+    Rank::Four,
+    Rank::Five,
+    Rank::Six,
+    Rank::Seven,
+    Rank::Eight,
+    Rank::Nine,
+    Rank::Ten,
+    Rank::Jack,
+    Rank::Queen,
+    Rank::King,
+    Rank::Ace,
+];
+
+/// Evaluates the best poker hand from 5 to 7 cards when some of them are wild.
+/// Each wild card stands in for whichever card makes the best hand, so five of a
+/// kind becomes possible.
+pub fn evaluate_hand_wild(cards: &[Card], ranking: HandRanking, wilds: Wilds) -> EvaluatedHand {
+    let mut best: Option<EvaluatedHand> = None;
+    for hand in combinations(cards, 5) {
+        let (wild, natural): (Vec<Card>, Vec<Card>) = hand.into_iter().partition(|c| wilds.is_wild(c));
+        let eval = best_substitution(&natural, wild.len(), ranking);
+        if best.as_ref().is_none_or(|b| ranking.compare(&eval, b) == Ordering::Greater) {
+            best = Some(eval);
+        }
+    }
+    best.unwrap_or(EvaluatedHand { rank: HandRank::HighCard, cards: vec![] })
+}
+
+/// Best hand from the natural cards plus `wild_count` wild cards. Wild cards are
+/// interchangeable, so only each multiset of ranks is tried; they take the suit
+/// of the natural cards when those share one, so they can complete a flush.
+fn best_substitution(natural: &[Card], wild_count: usize, ranking: HandRanking) -> EvaluatedHand {
+    let suit = natural.first()
+        .map(|c| c.suit)
+        .filter(|&s| natural.iter().all(|c| c.suit == s))
+        .unwrap_or(Suit::Spades);
+    let mut best: Option<EvaluatedHand> = None;
+    let mut choice = vec![0; wild_count];
+    loop {
+        let mut hand = natural.to_vec();
+        hand.extend(choice.iter().map(|&i| Card { rank: NATURAL_RANKS[i], suit }));
+        let eval = evaluate_five(&hand, ranking);
+        if best.as_ref().is_none_or(|b| ranking.compare(&eval, b) == Ordering::Greater) {
+            best = Some(eval);
+        }
+        // Next non-decreasing sequence of rank indices
+        let Some(pos) = choice.iter().rposition(|&i| i < NATURAL_RANKS.len() - 1) else {
+            break;
+        };
+        choice[pos] += 1;
+        for j in pos + 1..wild_count {
+            choice[j] = choice[pos];
+        }
+    }
+    best.unwrap()
+}
+
+/// Evaluates the cards a player shows face up in stud (1 to 4 cards). Only pairs,
+/// two pair, trips and quads count; straights and flushes need five cards.
+pub fn evaluate_visible(cards: &[Card]) -> EvaluatedHand {
+    if cards.len() >= 5 {
+        return evaluate_hand(cards);
+    }
+    let mut rank_counts: HashMap<Rank, usize> = HashMap::new();
+    for c in cards {
+        *rank_counts.entry(c.rank).or_insert(0) += 1;
+    }
+    let mut sorted = cards.to_vec();
+    sorted.sort_by_key(|c| std::cmp::Reverse((rank_counts[&c.rank], c.rank)));
+    let pairs = rank_counts.values().filter(|&&n| n == 2).count();
+    let rank = match rank_counts.values().max() {
+        Some(4) => HandRank::FourOfAKind,
+        Some(3) => HandRank::ThreeOfAKind,
+        Some(2) if pairs == 2 => HandRank::TwoPair,
+        Some(2) => HandRank::OnePair,
+        _ => HandRank::HighCard,
+    };
+    EvaluatedHand { rank, cards: sorted }
+}
+
+/// Evaluates the best Omaha high hand: exactly 2 hole cards and exactly 3 board cards
+pub fn evaluate_omaha_high(hole_cards: &[Card], board: &[Card]) -> EvaluatedHand {
+    let mut best = EvaluatedHand {
+        rank: HandRank::HighCard,
+        cards: vec![],
+    };
+    for hole in combinations(hole_cards, 2) {
+        for common in combinations(board, 3) {
+            let mut hand = hole.clone();
+            hand.extend(common);
+            let eval = evaluate_five(&hand, HandRanking::Standard);
+            if HandRanking::Standard.compare(&eval, &best) == Ordering::Greater {
+                best = eval;
+            }
+        }
+    }
+    best
+}
+
+/// An ace-to-five low hand. A lower hand compares as `Less`, so the best low is the minimum.
+/// Straights and flushes do not count; pairs make a hand worse.
+#[derive(Debug, Clone)]
+pub struct LowHand {
+    pub cards: Vec<Card>, // in comparison order: worst groups first, then highest card first
+    /// How badly the hand is made (pairs, or any made hand in deuce-to-seven),
+    /// then the card values in comparison order
+    key: (u8, Vec<u8>),
+    /// The made hand in deuce-to-seven, shown alongside the cards
+    made: Option<HandRank>,
+}
+
+impl LowHand {
+    /// Builds an ace-to-five low hand, ordering the cards the way they are compared
+    fn new(cards: &[Card]) -> Self {
+        let mut counts: HashMap<u8, usize> = HashMap::new();
+        for c in cards {
+            *counts.entry(low_value(c.rank)).or_insert(0) += 1;
+        }
+        let mut sorted = cards.to_vec();
+        sorted.sort_by_key(|c| std::cmp::Reverse((counts[&low_value(c.rank)], low_value(c.rank))));
+        let values: Vec<u8> = sorted.iter().map(|c| low_value(c.rank)).collect();
+        LowHand { key: (pairing(&values), values), cards: sorted, made: None }
+    }
+
+    /// Builds a deuce-to-seven low from a high hand evaluated without the wheel:
+    /// the weaker the high hand, the better the low
+    fn deuce_to_seven(hand: EvaluatedHand) -> Self {
+        let values: Vec<u8> = hand.cards.iter().map(|c| c.rank as u8).collect();
+        let made = (hand.rank != HandRank::HighCard).then_some(hand.rank);
+        LowHand { key: (hand.rank as u8, values), cards: hand.cards, made }
+    }
+
+    /// Card values in comparison order (ace counts as 1 in ace-to-five)
+    pub fn values(&self) -> Vec<u8> {
+        self.key.1.clone()
+    }
+}
+
+/// How badly sorted values are paired: 0 for no pair, then one pair, two pair,
+/// trips, full house and quads
+fn pairing(values: &[u8]) -> u8 {
+    let mut groups: Vec<usize> = values.chunk_by(|a, b| a == b).map(|g| g.len()).collect();
+    groups.sort_by_key(|&n| std::cmp::Reverse(n));
+    match (groups.first().copied().unwrap_or(0), groups.get(1).copied().unwrap_or(0)) {
+        (4, _) => 5,
+        (3, 2) => 4,
+        (3, _) => 3,
+        (2, 2) => 2,
+        (2, _) => 1,
+        _ => 0,
+    }
+}
+
+impl fmt::Display for LowHand {
+    /// Formats the hand highest card first, e.g. `8-6-4-3-A`, naming any
+    /// deuce-to-seven made hand, e.g. `Straight 7-6-5-4-3`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbols: Vec<&str> = self.cards.iter().map(|c| c.rank.symbol()).collect();
+        match self.made {
+            Some(rank) => write!(f, "{} {}", rank, symbols.join("-")),
+            None => write!(f, "{}", symbols.join("-")),
+        }
+    }
+}
+
+impl Ord for LowHand {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key.cmp(&other.key)
+    }
+}
+
+impl PartialEq for LowHand {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl Eq for LowHand {}
+
+impl PartialOrd for LowHand {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Evaluates the best ace-to-five low from any 5 of the given cards (Razz).
+/// Every hand qualifies; with fewer than 5 cards all of them are used.
+pub fn evaluate_razz(cards: &[Card]) -> LowHand {
+    if cards.len() <= 5 {
+        return LowHand::new(cards);
+    }
+    combinations(cards, 5).iter()
+        .map(|hand| LowHand::new(hand))
+        .min()
+        .unwrap()
+}
+
+/// Evaluates the best deuce-to-seven low from any 5 of the given cards. Aces are
+/// always high, and straights and flushes count against the hand, so 7-5-4-3-2
+/// of mixed suits is the best possible low.
+pub fn evaluate_deuce_seven(cards: &[Card]) -> LowHand {
+    combinations(cards, 5).into_iter()
+        .map(|hand| LowHand::deuce_to_seven(evaluate_five(&hand, HandRanking::DeuceToSeven)))
+        .min()
+        .unwrap()
+}
+
+/// Evaluates the best Omaha eight-or-better low: exactly 2 hole cards and exactly 3 board cards
+pub fn evaluate_omaha_low(hole_cards: &[Card], board: &[Card]) -> Option<LowHand> {
+    let mut best: Option<LowHand> = None;
+    for hole in combinations(hole_cards, 2) {
+        for common in combinations(board, 3) {
+            let mut hand = hole.clone();
+            hand.extend(common);
+            if let Some(low) = low_eight_five(&hand)
+                && best.as_ref().is_none_or(|b| low < *b)
+            {
+                best = Some(low);
+            }
+        }
+    }
+    best
+}
+
+/// Returns the low hand if the 5 cards are unpaired and all eight or lower
+fn low_eight_five(cards: &[Card]) -> Option<LowHand> {
+    let low = LowHand::new(cards);
+    if low.key.0 > 0 || low.values()[0] > 8 {
+        return None;
+    }
+    Some(low)
+}
+
+/// Value of a rank in ace-to-five lowball, where the ace is the lowest card
+pub fn low_value(rank: Rank) -> u8 {
+    if rank == Rank::Ace { 1 } else { rank as u8 }
+}
+
+/// All k-card combinations of the given cards, in input order
+fn combinations(cards: &[Card], k: usize) -> Vec<Vec<Card>> {
+    if k == 0 {
+        return vec![vec![]];
+    }
+    if cards.len() < k {
+        return vec![];
+    }
+    let mut result = Vec::new();
+    for (i, &card) in cards.iter().enumerate() {
+        for mut rest in combinations(&cards[i + 1..], k - 1) {
+            rest.insert(0, card);
+            result.push(rest);
+        }
+    }
+    result
+}
+
+fn evaluate_five(cards: &[Card], ranking: HandRanking) -> EvaluatedHand {
     let mut sorted = cards.to_vec();
-    sorted.sort_by(|a, b| b.rank.cmp(&a.rank));
+    sorted.sort_by_key(|c| std::cmp::Reverse(c.rank));
     let is_flush = sorted.iter().all(|c| c.suit == sorted[0].suit);
-    let straight_high = straight_high_card(&sorted);
+    let straight_high = straight_high_card(&sorted, ranking);
     // Straight flush and royal flush
-    if is_flush && straight_high.is_some() {
-        let high = straight_high.unwrap();
-        if high == Rank::Ace {
-            return EvaluatedHand { rank: HandRank::RoyalFlush, cards: sorted.clone() };
-        } else {
-            return EvaluatedHand { rank: HandRank::StraightFlush, cards: sorted.clone() };
+    if let Some(high) = straight_high {
+        // A wheel plays the ace as the lowest card
+        if high != Rank::Ace && sorted[0].rank == Rank::Ace {
+            sorted.rotate_left(1);
+        }
+        if is_flush && high == Rank::Ace {
+            return EvaluatedHand { rank: HandRank::RoyalFlush, cards: sorted };
+        }
+        if is_flush {
+            return EvaluatedHand { rank: HandRank::StraightFlush, cards: sorted };
         }
     }
     // Four of a kind, full house, three of a kind, two pair, pair
@@ -93,6 +442,9 @@ fn evaluate_five(cards: &[Card]) -> EvaluatedHand {
     }
     let mut counts: Vec<(Rank, usize)> = rank_counts.iter().map(|(&r, &c)| (r, c)).collect();
     counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| b.0.cmp(&a.0)));
+    if counts[0].1 == 5 {
+        return EvaluatedHand { rank: HandRank::FiveOfAKind, cards: sorted };
+    }
     if counts[0].1 == 4 {
         // Four of a kind
         let kicker = sorted.iter().find(|c| c.rank != counts[0].0).unwrap();
@@ -107,10 +459,10 @@ fn evaluate_five(cards: &[Card]) -> EvaluatedHand {
         return EvaluatedHand { rank: HandRank::FullHouse, cards: hand };
     }
     if is_flush {
-        return EvaluatedHand { rank: HandRank::Flush, cards: sorted.clone() };
+        return EvaluatedHand { rank: HandRank::Flush, cards: sorted };
     }
     if straight_high.is_some() {
-        return EvaluatedHand { rank: HandRank::Straight, cards: sorted.clone() };
+        return EvaluatedHand { rank: HandRank::Straight, cards: sorted };
     }
     if counts[0].1 == 3 {
         // Three of a kind
@@ -122,7 +474,7 @@ fn evaluate_five(cards: &[Card]) -> EvaluatedHand {
         // Two pair
         let mut hand = sorted.iter().filter(|c| c.rank == counts[0].0).cloned().collect::<Vec<_>>();
         hand.extend(sorted.iter().filter(|c| c.rank == counts[1].0).cloned());
-        hand.push(sorted.iter().find(|c| c.rank != counts[0].0 && c.rank != counts[1].0).unwrap().clone());
+        hand.push(*sorted.iter().find(|c| c.rank != counts[0].0 && c.rank != counts[1].0).unwrap());
         return EvaluatedHand { rank: HandRank::TwoPair, cards: hand };
     }
     if counts[0].1 == 2 {
@@ -132,12 +484,12 @@ fn evaluate_five(cards: &[Card]) -> EvaluatedHand {
         return EvaluatedHand { rank: HandRank::OnePair, cards: hand };
     }
     // High card
-    EvaluatedHand { rank: HandRank::HighCard, cards: sorted.clone() }
+    EvaluatedHand { rank: HandRank::HighCard, cards: sorted }
 }
 
-fn straight_high_card(cards: &[Card]) -> Option<Rank> {
+fn straight_high_card(cards: &[Card], ranking: HandRanking) -> Option<Rank> {
     let mut ranks: Vec<Rank> = cards.iter().map(|c| c.rank).collect();
-    ranks.sort_by(|a, b| b.cmp(a));
+    ranks.sort_by_key(|&r| std::cmp::Reverse(r));
     ranks.dedup();
     if ranks.len() < 5 {
         return None;
@@ -147,14 +499,12 @@ fn straight_high_card(cards: &[Card]) -> Option<Rank> {
             return Some(ranks[i]);
         }
     }
-    // Wheel: A-2-3-4-5
-    if ranks.contains(&Rank::Ace)
-        && ranks.contains(&Rank::Five)
-        && ranks.contains(&Rank::Four)
-        && ranks.contains(&Rank::Three)
-        && ranks.contains(&Rank::Two)
+    // Wheel: A-2-3-4-5, or A-6-7-8-9 in short-deck
+    if let Some(wheel) = ranking.wheel_ranks()
+        && ranks.contains(&Rank::Ace)
+        && wheel.iter().all(|r| ranks.contains(r))
     {
-        return Some(Rank::Five);
+        return Some(wheel[3]);
     }
     None
 }
@@ -179,4 +529,285 @@ mod tests {
         assert_eq!(hand.rank, HandRank::HighCard);
         assert_eq!(hand.cards[0].rank, Rank::Ace);
     }
+
+    fn card(rank: Rank, suit: Suit) -> Card {
+        Card { rank, suit }
+    }
+
+    #[test]
+    fn test_wheel_loses_to_six_high_straight() {
+        let wheel = evaluate_hand(&[
+            card(Rank::Ace, Suit::Spades),
+            card(Rank::Two, Suit::Hearts),
+            card(Rank::Three, Suit::Diamonds),
+            card(Rank::Four, Suit::Clubs),
+            card(Rank::Five, Suit::Spades),
+        ]);
+        let six_high = evaluate_hand(&[
+            card(Rank::Six, Suit::Spades),
+            card(Rank::Two, Suit::Hearts),
+            card(Rank::Three, Suit::Diamonds),
+            card(Rank::Four, Suit::Clubs),
+            card(Rank::Five, Suit::Spades),
+        ]);
+        assert_eq!(wheel.rank, HandRank::Straight);
+        assert_eq!(HandRanking::Standard.compare(&six_high, &wheel), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_suits_do_not_break_ties() {
+        let a = evaluate_hand(&[
+            card(Rank::Ace, Suit::Spades),
+            card(Rank::King, Suit::Spades),
+            card(Rank::Nine, Suit::Hearts),
+            card(Rank::Seven, Suit::Diamonds),
+            card(Rank::Four, Suit::Clubs),
+        ]);
+        let b = evaluate_hand(&[
+            card(Rank::Ace, Suit::Hearts),
+            card(Rank::King, Suit::Clubs),
+            card(Rank::Nine, Suit::Spades),
+            card(Rank::Seven, Suit::Clubs),
+            card(Rank::Four, Suit::Diamonds),
+        ]);
+        assert_eq!(HandRanking::Standard.compare(&a, &b), Ordering::Equal);
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_omaha_uses_exactly_two_hole_cards() {
+        // Four spades on board plus one in hand is not a flush in Omaha
+        let hole = [
+            card(Rank::Ace, Suit::Spades),
+            card(Rank::King, Suit::Hearts),
+            card(Rank::Queen, Suit::Hearts),
+            card(Rank::Two, Suit::Clubs),
+        ];
+        let board = [
+            card(Rank::Three, Suit::Spades),
+            card(Rank::Seven, Suit::Spades),
+            card(Rank::Nine, Suit::Spades),
+            card(Rank::Jack, Suit::Spades),
+            card(Rank::Four, Suit::Diamonds),
+        ];
+        assert_ne!(evaluate_omaha_high(&hole, &board).rank, HandRank::Flush);
+    }
+
+    #[test]
+    fn test_omaha_low_qualifies_only_eight_or_better() {
+        let hole = [
+            card(Rank::Ace, Suit::Spades),
+            card(Rank::Two, Suit::Hearts),
+            card(Rank::King, Suit::Hearts),
+            card(Rank::King, Suit::Clubs),
+        ];
+        let low_board = [
+            card(Rank::Three, Suit::Spades),
+            card(Rank::Seven, Suit::Diamonds),
+            card(Rank::Eight, Suit::Clubs),
+            card(Rank::Queen, Suit::Spades),
+            card(Rank::Jack, Suit::Diamonds),
+        ];
+        let low = evaluate_omaha_low(&hole, &low_board).unwrap();
+        assert_eq!(low.values(), vec![8, 7, 3, 2, 1]);
+
+        let high_board = [
+            card(Rank::Three, Suit::Spades),
+            card(Rank::Seven, Suit::Diamonds),
+            card(Rank::Nine, Suit::Clubs),
+            card(Rank::Queen, Suit::Spades),
+            card(Rank::Jack, Suit::Diamonds),
+        ];
+        assert!(evaluate_omaha_low(&hole, &high_board).is_none());
+    }
+
+    #[test]
+    fn test_short_deck_flush_beats_full_house() {
+        let ranking = HandRanking::ShortDeck { trips_beat_straight: true };
+        let flush = evaluate_hand_with(&[
+            card(Rank::Ace, Suit::Hearts),
+            card(Rank::Jack, Suit::Hearts),
+            card(Rank::Nine, Suit::Hearts),
+            card(Rank::Seven, Suit::Hearts),
+            card(Rank::Six, Suit::Hearts),
+        ], ranking);
+        let full_house = evaluate_hand_with(&[
+            card(Rank::King, Suit::Spades),
+            card(Rank::King, Suit::Clubs),
+            card(Rank::King, Suit::Diamonds),
+            card(Rank::Seven, Suit::Spades),
+            card(Rank::Seven, Suit::Clubs),
+        ], ranking);
+        assert_eq!(ranking.compare(&flush, &full_house), Ordering::Greater);
+        // Standard ranking still puts the full house on top
+        assert_eq!(HandRanking::Standard.compare(&flush, &full_house), Ordering::Less);
+    }
+
+    #[test]
+    fn test_short_deck_trips_versus_straight_is_configurable() {
+        let trips_cards = [
+            card(Rank::Eight, Suit::Spades),
+            card(Rank::Eight, Suit::Clubs),
+            card(Rank::Eight, Suit::Diamonds),
+            card(Rank::King, Suit::Spades),
+            card(Rank::Seven, Suit::Clubs),
+        ];
+        let straight_cards = [
+            card(Rank::Ten, Suit::Spades),
+            card(Rank::Jack, Suit::Clubs),
+            card(Rank::Queen, Suit::Diamonds),
+            card(Rank::King, Suit::Hearts),
+            card(Rank::Nine, Suit::Clubs),
+        ];
+        for (trips_beat_straight, expected) in [(true, Ordering::Greater), (false, Ordering::Less)] {
+            let ranking = HandRanking::ShortDeck { trips_beat_straight };
+            let trips = evaluate_hand_with(&trips_cards, ranking);
+            let straight = evaluate_hand_with(&straight_cards, ranking);
+            assert_eq!(ranking.compare(&trips, &straight), expected);
+        }
+    }
+
+    #[test]
+    fn test_short_deck_ace_six_straight() {
+        let ranking = HandRanking::ShortDeck { trips_beat_straight: true };
+        let wheel = evaluate_hand_with(&[
+            card(Rank::Ace, Suit::Spades),
+            card(Rank::Six, Suit::Hearts),
+            card(Rank::Seven, Suit::Diamonds),
+            card(Rank::Eight, Suit::Clubs),
+            card(Rank::Nine, Suit::Spades),
+            card(Rank::King, Suit::Spades),
+            card(Rank::King, Suit::Clubs),
+        ], ranking);
+        assert_eq!(wheel.rank, HandRank::Straight);
+        assert_eq!(wheel.cards[0].rank, Rank::Nine);
+        assert_eq!(wheel.cards[4].rank, Rank::Ace);
+    }
+
+    #[test]
+    fn test_razz_low_ignores_straights_and_penalizes_pairs() {
+        let hand = evaluate_razz(&[
+            card(Rank::Ace, Suit::Spades),
+            card(Rank::Two, Suit::Spades),
+            card(Rank::Three, Suit::Spades),
+            card(Rank::Four, Suit::Spades),
+            card(Rank::Five, Suit::Spades),
+            card(Rank::King, Suit::Hearts),
+            card(Rank::King, Suit::Clubs),
+        ]);
+        assert_eq!(hand.to_string(), "5-4-3-2-A");
+
+        let paired = evaluate_razz(&[
+            card(Rank::Ace, Suit::Spades),
+            card(Rank::Ace, Suit::Hearts),
+            card(Rank::Two, Suit::Spades),
+            card(Rank::Three, Suit::Clubs),
+            card(Rank::Four, Suit::Diamonds),
+            card(Rank::Four, Suit::Hearts),
+            card(Rank::Two, Suit::Clubs),
+        ]);
+        let king_low = evaluate_razz(&[
+            card(Rank::King, Suit::Spades),
+            card(Rank::Queen, Suit::Hearts),
+            card(Rank::Jack, Suit::Spades),
+            card(Rank::Ten, Suit::Clubs),
+            card(Rank::Nine, Suit::Diamonds),
+        ]);
+        assert_eq!(paired.to_string(), "A-A-4-3-2");
+        assert!(king_low < paired);
+    }
+
+    #[test]
+    fn test_deuce_seven_aces_high_and_straights_count() {
+        let seven_five = evaluate_deuce_seven(&[
+            card(Rank::Seven, Suit::Spades),
+            card(Rank::Five, Suit::Hearts),
+            card(Rank::Four, Suit::Diamonds),
+            card(Rank::Three, Suit::Clubs),
+            card(Rank::Two, Suit::Spades),
+        ]);
+        let ace_high = evaluate_deuce_seven(&[
+            card(Rank::Ace, Suit::Spades),
+            card(Rank::Five, Suit::Hearts),
+            card(Rank::Four, Suit::Diamonds),
+            card(Rank::Three, Suit::Clubs),
+            card(Rank::Two, Suit::Spades),
+        ]);
+        let straight = evaluate_deuce_seven(&[
+            card(Rank::Seven, Suit::Spades),
+            card(Rank::Six, Suit::Hearts),
+            card(Rank::Five, Suit::Diamonds),
+            card(Rank::Four, Suit::Clubs),
+            card(Rank::Three, Suit::Spades),
+        ]);
+        let flush = evaluate_deuce_seven(&[
+            card(Rank::Seven, Suit::Spades),
+            card(Rank::Five, Suit::Spades),
+            card(Rank::Four, Suit::Spades),
+            card(Rank::Three, Suit::Spades),
+            card(Rank::Two, Suit::Spades),
+        ]);
+        let king_high = evaluate_deuce_seven(&[
+            card(Rank::King, Suit::Spades),
+            card(Rank::Nine, Suit::Hearts),
+            card(Rank::Eight, Suit::Diamonds),
+            card(Rank::Four, Suit::Clubs),
+            card(Rank::Two, Suit::Spades),
+        ]);
+        assert_eq!(seven_five.to_string(), "7-5-4-3-2");
+        assert_eq!(ace_high.to_string(), "A-5-4-3-2");
+        assert_eq!(straight.to_string(), "Straight 7-6-5-4-3");
+        assert!(seven_five < king_high);
+        assert!(king_high < ace_high);
+        assert!(ace_high < straight);
+        assert!(king_high < flush);
+    }
+
+    #[test]
+    fn test_joker_makes_five_of_a_kind_above_royal_flush() {
+        let jokers = Wilds { jokers: true, rank: None };
+        let five_aces = evaluate_hand_wild(&[
+            card(Rank::Ace, Suit::Spades),
+            card(Rank::Ace, Suit::Hearts),
+            card(Rank::Ace, Suit::Diamonds),
+            card(Rank::Ace, Suit::Clubs),
+            card(Rank::Joker, Suit::Spades),
+            card(Rank::Two, Suit::Hearts),
+            card(Rank::Seven, Suit::Clubs),
+        ], HandRanking::Standard, jokers);
+        let royal = evaluate_hand(&[
+            card(Rank::Ace, Suit::Spades),
+            card(Rank::King, Suit::Spades),
+            card(Rank::Queen, Suit::Spades),
+            card(Rank::Jack, Suit::Spades),
+            card(Rank::Ten, Suit::Spades),
+        ]);
+        assert_eq!(five_aces.rank, HandRank::FiveOfAKind);
+        assert_eq!(HandRanking::Standard.compare(&five_aces, &royal), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_deuces_wild_picks_best_substitution() {
+        let deuces = Wilds { jokers: false, rank: Some(Rank::Two) };
+        let hand = evaluate_hand_wild(&[
+            card(Rank::Nine, Suit::Hearts),
+            card(Rank::Eight, Suit::Hearts),
+            card(Rank::Two, Suit::Clubs),
+            card(Rank::Six, Suit::Hearts),
+            card(Rank::Two, Suit::Spades),
+            card(Rank::King, Suit::Clubs),
+        ], HandRanking::Standard, deuces);
+        assert_eq!(hand.rank, HandRank::StraightFlush);
+        assert_eq!(hand.ranks(), vec![Rank::Ten, Rank::Nine, Rank::Eight, Rank::Seven, Rank::Six]);
+
+        // Without wild cards the result matches the plain evaluator
+        let cards = [
+            card(Rank::King, Suit::Spades),
+            card(Rank::King, Suit::Hearts),
+            card(Rank::Four, Suit::Diamonds),
+            card(Rank::Nine, Suit::Clubs),
+            card(Rank::Jack, Suit::Spades),
+        ];
+        assert_eq!(evaluate_hand_wild(&cards, HandRanking::Standard, Wilds::default()), evaluate_hand(&cards));
+    }
 }
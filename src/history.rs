@@ -0,0 +1,174 @@
+use crate::card::Card;
+use serde::Serialize;
+use std::fmt;
+
+/// A move as the whole table sees it, with the chips it put in. Draws and
+/// discards only say how many cards changed hands.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ActionTaken {
+    Fold,
+    Check,
+    Call { amount: f64 },
+    /// A bet or raise of `amount` over the call, making the bet `to`
+    Bet { amount: f64, to: f64 },
+    /// The player's whole stack went in
+    AllIn { amount: f64 },
+    Draw { count: usize },
+    Discard,
+    /// A vote to run the rest of the board `runs` times
+    Vote { runs: usize },
+    /// The player turned their hand over at showdown
+    Show { cards: Vec<Card> },
+    /// The player threw their losing hand away unseen at showdown
+    Muck,
+}
+
+impl fmt::Display for ActionTaken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ActionTaken::Fold => write!(f, "folds"),
+            ActionTaken::Check => write!(f, "checks"),
+            ActionTaken::Call { amount } => write!(f, "calls {}", amount),
+            ActionTaken::Bet { amount, .. } => write!(f, "bets {}", amount),
+            ActionTaken::AllIn { amount } => write!(f, "goes all-in for {}", amount),
+            ActionTaken::Draw { count: 0 } => write!(f, "stands pat"),
+            ActionTaken::Draw { count } => write!(f, "draws {}", count),
+            ActionTaken::Discard => write!(f, "discards a card"),
+            ActionTaken::Vote { runs } => write!(f, "votes to run it {} time(s)", runs),
+            ActionTaken::Show { cards } => write!(f, "shows {}", join_cards(cards)),
+            ActionTaken::Muck => write!(f, "mucks"),
+        }
+    }
+}
+
+/// Something that happened during a hand, in the order it happened
+#[derive(Debug, Clone, PartialEq)]
+pub enum HandEvent {
+    /// A new hand was dealt to these players
+    Started { variant: String, players: Vec<String> },
+    /// A forced bet: blind, ante or bring-in
+    Posted { player: String, amount: f64, kind: &'static str },
+    /// Face-down cards dealt to a player; only that player sees them
+    HoleCards { player: String, cards: Vec<Card> },
+    /// Face-up cards dealt to a player (stud)
+    UpCards { player: String, cards: Vec<Card> },
+    /// Community cards dealt to the board
+    Board { cards: Vec<Card> },
+    /// The cards completing one run of the board when it is run more than once
+    Run { run: usize, cards: Vec<Card> },
+    /// Cards dealt to one of the boards in a double-board bomb pot
+    DoubleBoard { board: usize, cards: Vec<Card> },
+    /// A betting action
+    Action { player: String, action: ActionTaken },
+    /// Cards replaced in a draw; everyone sees how many, only the player sees which
+    Draw { player: String, discarded: Vec<Card>, received: Vec<Card> },
+    /// A card thrown away in Pineapple; it is never shown to anyone else
+    Discard { player: String, card: Card },
+    /// Cards shown at showdown, visible to everyone
+    Shows { player: String, cards: Vec<Card> },
+    /// A hand thrown away unseen at showdown
+    Mucks { player: String },
+    /// Chips awarded from a pot, with the winning hand if it was shown down
+    Won { player: String, amount: f64, hand: Option<String> },
+}
+
+/// The record of a single hand
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HandHistory {
+    pub events: Vec<HandEvent>,
+}
+
+impl HandHistory {
+    pub fn record(&mut self, event: HandEvent) {
+        self.events.push(event);
+    }
+
+    /// The hand as `viewer` is allowed to see it: their own hole cards, draws and
+    /// discards are shown, everyone else's are only counted.
+    pub fn view(&self, viewer: &str) -> Vec<EventView> {
+        self.events.iter().map(|event| {
+            let own = |player: &String| player == viewer;
+            match event.clone() {
+                HandEvent::Started { variant, players } => EventView::Started { variant, players },
+                HandEvent::Posted { player, amount, kind } => EventView::Posted { player, amount, kind },
+                HandEvent::HoleCards { player, cards } => EventView::HoleCards {
+                    count: cards.len(),
+                    cards: own(&player).then_some(cards),
+                    player,
+                },
+                HandEvent::UpCards { player, cards } => EventView::UpCards { player, cards },
+                HandEvent::Board { cards } => EventView::Board { cards },
+                HandEvent::Run { run, cards } => EventView::Run { run, cards },
+                HandEvent::DoubleBoard { board, cards } => EventView::DoubleBoard { board, cards },
+                HandEvent::Action { player, action } => EventView::Action { player, action },
+                HandEvent::Draw { player, discarded, received } => EventView::Draw {
+                    count: discarded.len(),
+                    cards: own(&player).then_some((discarded, received)),
+                    player,
+                },
+                HandEvent::Discard { player, card } => EventView::Discard { card: own(&player).then_some(card), player },
+                HandEvent::Shows { player, cards } => EventView::Shows { player, cards },
+                HandEvent::Mucks { player } => EventView::Mucks { player },
+                HandEvent::Won { player, amount, hand } => EventView::Won { player, amount, hand },
+            }
+        }).collect()
+    }
+}
+
+/// Renders a hand as seen by one player, one event per line
+pub fn render(events: &[EventView]) -> String {
+    events.iter().map(|event| format!("{}\n", event)).collect()
+}
+
+/// A hand event as one player sees it. Cards they are not allowed to see are left
+/// out and only counted.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum EventView {
+    Started { variant: String, players: Vec<String> },
+    Posted { player: String, amount: f64, kind: &'static str },
+    /// `cards` is only filled in for the viewer's own hole cards
+    HoleCards { player: String, cards: Option<Vec<Card>>, count: usize },
+    UpCards { player: String, cards: Vec<Card> },
+    Board { cards: Vec<Card> },
+    Run { run: usize, cards: Vec<Card> },
+    DoubleBoard { board: usize, cards: Vec<Card> },
+    Action { player: String, action: ActionTaken },
+    /// `count` cards replaced; the viewer's own draw also gives the cards thrown and received
+    Draw { player: String, count: usize, cards: Option<(Vec<Card>, Vec<Card>)> },
+    Discard { player: String, card: Option<Card> },
+    Shows { player: String, cards: Vec<Card> },
+    Mucks { player: String },
+    Won { player: String, amount: f64, hand: Option<String> },
+}
+
+impl fmt::Display for EventView {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EventView::Started { variant, players } => write!(f, "{} hand: {}", variant, players.join(", ")),
+            EventView::Posted { player, amount, kind } => write!(f, "{} posts {} {}", player, kind, amount),
+            EventView::HoleCards { player, cards: Some(cards), .. } => write!(f, "Dealt to {}: {}", player, join_cards(cards)),
+            EventView::HoleCards { player, count, .. } => write!(f, "Dealt to {}: {} hidden card(s)", player, count),
+            EventView::UpCards { player, cards } => write!(f, "Dealt to {} face up: {}", player, join_cards(cards)),
+            EventView::Board { cards } => write!(f, "Board: {}", join_cards(cards)),
+            EventView::Run { run, cards } => write!(f, "Run {}: {}", run, join_cards(cards)),
+            EventView::DoubleBoard { board, cards } => write!(f, "Board {}: {}", board, join_cards(cards)),
+            EventView::Action { player, action } => write!(f, "{} {}", player, action),
+            EventView::Draw { player, cards: Some((discarded, received)), .. } if !discarded.is_empty() => {
+                write!(f, "{} discards {} and draws {}", player, join_cards(discarded), join_cards(received))
+            }
+            EventView::Draw { player, count, .. } => write!(f, "{} {}", player, ActionTaken::Draw { count: *count }),
+            EventView::Discard { player, card: Some(card) } => write!(f, "{} discards {}", player, card),
+            EventView::Discard { player, card: None } => write!(f, "{} discards a card", player),
+            EventView::Shows { player, cards } => write!(f, "{} shows {}", player, join_cards(cards)),
+            EventView::Mucks { player } => write!(f, "{} mucks", player),
+            EventView::Won { player, amount, hand: Some(hand) } => write!(f, "{} wins {} with {}", player, amount, hand),
+            EventView::Won { player, amount, hand: None } => write!(f, "{} wins {}", player, amount),
+        }
+    }
+}
+
+fn join_cards(cards: &[Card]) -> String {
+    cards.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(" ")
+}
@@ -1,16 +1,37 @@
+mod account;
 mod card;
+mod chat;
+mod clock;
+mod config;
 mod deck;
 mod game;
 mod hand;
+mod history;
 mod player;
 mod models;
 mod commands;
 mod network;
+mod protocol;
+mod session;
+#[cfg(test)]
+mod test_support;
 mod utils;
+mod variant;
+mod view;
+mod websocket;
 
+use crate::config::{Cli, Config};
 use crate::network::start_server;
+use clap::Parser;
 
 #[tokio::main]
 async fn main() {
-    start_server().await;
+    let config = Config::load(&Cli::parse()).unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    });
+    if let Err(e) = start_server(config).await {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
 }
\ No newline at end of file
@@ -1,5 +1,12 @@
 use std::collections::{HashMap, HashSet};
-use crate::game::Game;
+use crate::account::Account;
+use crate::chat::ChatFilter;
+use crate::clock::ActionClock;
+use crate::config::Config;
+use crate::game::{BombPotRules, ClockRules, Game};
+use crate::protocol::ServerMessage;
+use crate::variant::GameVariant;
+use std::sync::Arc;
 use tokio::sync::mpsc::UnboundedSender;
 
 /// Unique identifier for a user
@@ -14,8 +21,14 @@ pub struct User {
     pub name: String,
     /// User's current balance
     pub balance: f64,
+    /// Password hash and login attempts
+    pub account: Account,
     /// Table the user is currently sitting at (if any)
     pub table: Option<TableId>,
+    /// Table the user is watching without a seat (if any)
+    pub watching: Option<TableId>,
+    /// Users whose chat this user does not receive
+    pub muted: HashSet<UserId>,
 }
 
 /// Represents a poker table
@@ -23,10 +36,97 @@ pub struct User {
 pub struct Table {
     /// Table's unique identifier
     pub id: TableId,
+    /// User who created the table, if they were registered; only they can call bomb pots
+    pub host: Option<UserId>,
+    /// Poker variant played at this table
+    pub variant: Arc<dyn GameVariant>,
+    /// Options chosen when the table was created
+    pub settings: TableSettings,
     /// Set of user IDs of players at the table
     pub players: HashSet<UserId>,
+    /// Users watching the table without a seat
+    pub spectators: HashSet<UserId>,
     /// The current game at the table (if any)
     pub game: Option<Game>,
+    /// Clock running for the player whose turn it is
+    pub clock: Option<ActionClock>,
+}
+
+impl Table {
+    /// Everyone who follows the table's events: its players and spectators
+    pub fn audience(&self) -> HashSet<UserId> {
+        self.players.union(&self.spectators).cloned().collect()
+    }
+}
+
+/// Per-table options, set as `key=value` arguments to CREATE_TABLE
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableSettings {
+    /// How many times per hand the undealt board can be revealed with RABBIT (0 turns it off)
+    pub rabbit_hunts: usize,
+    /// When bomb pots are played and how big they are
+    pub bomb_pots: BombPotRules,
+    /// Whether every hand is turned face up at showdown once a player is all-in
+    pub all_in_face_up: bool,
+    /// How long players have to act, and their time banks
+    pub clock: ClockRules,
+    /// Most users that can watch the table at once
+    pub max_spectators: usize,
+}
+
+impl Default for TableSettings {
+    fn default() -> Self {
+        TableSettings { rabbit_hunts: 1, bomb_pots: BombPotRules::default(), all_in_face_up: false, clock: ClockRules::default(), max_spectators: 20 }
+    }
+}
+
+impl TableSettings {
+    /// Sets one option from its `key=value` form
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "rabbit" => {
+                self.rabbit_hunts = value.parse().map_err(|_| format!("Invalid value for rabbit: {}", value))?;
+            }
+            "bomb_every" => {
+                self.bomb_pots.every = value.parse().map_err(|_| format!("Invalid value for bomb_every: {}", value))?;
+            }
+            "bomb_ante" => {
+                self.bomb_pots.ante = value.parse::<f64>()
+                    .ok()
+                    .filter(|ante| ante.is_finite() && *ante > 0.0)
+                    .ok_or_else(|| format!("Invalid value for bomb_ante: {}", value))?;
+            }
+            "all_in_face_up" => {
+                self.all_in_face_up = value.parse().map_err(|_| format!("Invalid value for all_in_face_up: {}", value))?;
+            }
+            "bomb_double" => {
+                self.bomb_pots.double_board = value.parse().map_err(|_| format!("Invalid value for bomb_double: {}", value))?;
+            }
+            "action_time" => {
+                self.clock.action_secs = value.parse::<u64>()
+                    .ok()
+                    .filter(|secs| *secs > 0)
+                    .ok_or_else(|| format!("Invalid value for action_time: {}", value))?;
+            }
+            "time_bank" => {
+                self.clock.time_bank_secs = value.parse().map_err(|_| format!("Invalid value for time_bank: {}", value))?;
+            }
+            "time_bank_refill" => {
+                self.clock.refill_secs = value.parse().map_err(|_| format!("Invalid value for time_bank_refill: {}", value))?;
+            }
+            "disconnect_grace" => {
+                self.clock.disconnect_grace_secs = value.parse().map_err(|_| format!("Invalid value for disconnect_grace: {}", value))?;
+            }
+            "sit_out_after" => {
+                self.clock.sit_out_after_hands = value.parse().map_err(|_| format!("Invalid value for sit_out_after: {}", value))?;
+            }
+            "max_spectators" => {
+                self.max_spectators = value.parse().map_err(|_| format!("Invalid value for max_spectators: {}", value))?;
+            }
+            _ => return Err(format!("Unknown table option: {}", key)),
+        }
+        Ok(())
+    }
 }
 
 /// Global server state, shared between all connections
@@ -37,5 +137,31 @@ pub struct ServerState {
     /// All tables (by table ID)
     pub tables: HashMap<TableId, Table>,
     /// Channels for sending messages to users (by user ID)
-    pub writers: HashMap<UserId, UnboundedSender<String>>,
+    pub writers: HashMap<UserId, UnboundedSender<ServerMessage>>,
+    /// Session tokens, and the user each was issued to
+    pub sessions: HashMap<String, UserId>,
+    /// Checks run on every chat message, in order
+    pub chat_filters: Vec<Box<dyn ChatFilter>>,
+    /// Settings the server was started with
+    pub config: Config,
+    /// Clients connected right now, over TCP or WebSocket
+    pub connections: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_settings_reject_bad_values() {
+        let mut settings = TableSettings::default();
+        settings.set("bomb_ante", "2.5").unwrap();
+        assert_eq!(settings.bomb_pots.ante, 2.5);
+        for bad in ["0", "-1", "inf", "NaN", "lots"] {
+            assert_eq!(settings.set("bomb_ante", bad).unwrap_err(), format!("Invalid value for bomb_ante: {}", bad));
+        }
+        assert_eq!(settings.bomb_pots.ante, 2.5);
+        assert!(settings.set("action_time", "0").is_err());
+        assert!(settings.set("colour", "red").is_err());
+    }
 }
@@ -1,18 +1,45 @@
 use crate::models::*;
+use crate::config::Config;
+use crate::chat::{WordFilter, DEFAULT_BLOCKED_WORDS};
 use crate::commands::*;
+use crate::variant::{default_variant, parse_variant};
+use crate::protocol::{ClientMessage, Protocol, ServerMessage};
+use crate::view::PlayerView;
+use crate::websocket::serve_websocket;
+use tokio::net::tcp::OwnedReadHalf;
 use tokio::net::{TcpListener, TcpStream};
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
 use tokio::sync::mpsc::{unbounded_channel, UnboundedSender, UnboundedReceiver};
 use std::sync::{Arc, Mutex};
 
-/// Starts the TCP server and listens for incoming connections.
-pub async fn start_server() {
-    let state = Arc::new(Mutex::new(ServerState::default()));
-    let listener = TcpListener::bind("127.0.0.1:8080").await.unwrap();
-    println!("Server is running on port 8080");
+/// Greeting sent to every new connection, before the protocol is picked
+pub const BANNER: [&str; 2] = [
+    "Welcome to Poker Server!",
+    "Commands: PROTOCOL <text|json> (first message only), REGISTER <name> <password>, LOGIN <name> <password>, CHANGE_PASSWORD <old> <new>, RESUME <token>, CREATE_TABLE <table> [variant], JOIN_TABLE <table>, WATCH_TABLE <table>, UNWATCH, LIST_TABLES, SAY <text>, WHISPER <user> <text>, MUTE <user>, UNMUTE <user>, SHOW, QUIT",
+];
+
+/// Sent to a client turned away because the server has too many connections
+pub const SERVER_FULL: &str = "Server is full, try again later";
+
+/// Longest line, in bytes, a client may send; longer lines are skipped
+pub const MAX_LINE: usize = 4096;
+
+/// Starts the TCP server and the WebSocket gateway, which share one server state.
+/// Fails if either can not listen on its address.
+pub async fn start_server(config: Config) -> Result<(), String> {
+    let listener = bind(&config.server.bind_address, config.server.port).await?;
+    let ws_listener = bind(&config.server.bind_address, config.server.websocket_port).await?;
+    let mut server = ServerState { config, ..ServerState::default() };
+    server.chat_filters.push(Box::new(WordFilter::new(DEFAULT_BLOCKED_WORDS)));
+    let state = Arc::new(Mutex::new(server));
+    println!("Server is running on {}", listener.local_addr().map_err(|e| e.to_string())?);
+    println!("WebSocket gateway is running on {}", ws_listener.local_addr().map_err(|e| e.to_string())?);
+    tokio::spawn(serve_websocket(ws_listener, Arc::clone(&state)));
 
     loop {
-        let (socket, _) = listener.accept().await.unwrap();
+        let Ok((socket, _)) = listener.accept().await else {
+            continue;
+        };
         let state = Arc::clone(&state);
         tokio::spawn(async move {
             handle_client(socket, state).await;
@@ -20,132 +47,333 @@ pub async fn start_server() {
     }
 }
 
+async fn bind(address: &str, port: u16) -> Result<TcpListener, String> {
+    TcpListener::bind((address, port)).await
+        .map_err(|e| format!("Could not listen on {}:{}: {}", address, port, e))
+}
+
+/// A client counted against the server's `max_connections`, released when dropped
+pub struct ConnectionSlot(Arc<Mutex<ServerState>>);
+
+impl ConnectionSlot {
+    /// Takes a slot for a new client, or None if the server is full
+    pub fn acquire(state: &Arc<Mutex<ServerState>>) -> Option<ConnectionSlot> {
+        let mut guard = state.lock().unwrap();
+        if guard.connections >= guard.config.server.max_connections {
+            return None;
+        }
+        guard.connections += 1;
+        Some(ConnectionSlot(Arc::clone(state)))
+    }
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.0.lock() {
+            state.connections -= 1;
+        }
+    }
+}
+
 /// Handles a single client connection: reads commands, processes them, and sends responses.
+/// The first line may pick the protocol with `PROTOCOL <text|json>`; without it the
+/// connection uses text.
 pub async fn handle_client(socket: TcpStream, state: Arc<Mutex<ServerState>>) {
     let (reader, mut writer) = socket.into_split();
     let mut reader = BufReader::new(reader);
-    let mut line = String::new();
     let mut user_id: Option<UserId> = None;
+    let Some(_slot) = ConnectionSlot::acquire(&state) else {
+        let _ = writer.write_all(format!("{}\n", SERVER_FULL).as_bytes()).await;
+        return;
+    };
+
+    // The banner is always text, since the protocol has not been picked yet
+    for banner in BANNER {
+        let _ = writer.write_all(format!("{}\n", banner).as_bytes()).await;
+    }
+
+    // A first line that is not a handshake is the first text command
+    let Some(first) = next_line(&mut reader).await else {
+        return;
+    };
+    let handshake = first.as_deref().ok().and_then(Protocol::from_handshake);
+    let protocol = handshake.unwrap_or(Protocol::Text);
+    let mut pending = if handshake.is_some() { None } else { Some(first) };
 
     // Create a channel for sending messages to this client
-    let (tx, mut rx): (UnboundedSender<String>, UnboundedReceiver<String>) = unbounded_channel();
+    let (tx, mut rx): (UnboundedSender<ServerMessage>, UnboundedReceiver<ServerMessage>) = unbounded_channel();
 
     // Task for sending messages from the channel to the writer
     tokio::spawn(async move {
-        let mut writer = writer;
         while let Some(msg) = rx.recv().await {
-            let _ = writer.write_all(msg.as_bytes()).await;
+            let _ = writer.write_all(protocol.render(&msg).as_bytes()).await;
         }
     });
-
-    // After creating the channel and the task for the writer:
-    let _ = tx.send("Welcome to Poker Server!\n".to_string());
-    let _ = tx.send("Commands: REGISTER <name>, CREATE_TABLE <table>, JOIN_TABLE <table>, LIST_TABLES, SHOW, QUIT\n".to_string());
+    if handshake.is_some() {
+        acknowledge_protocol(protocol, &tx);
+    }
 
     loop {
-        line.clear();
-        let bytes = reader.read_line(&mut line).await.unwrap();
-        if bytes == 0 {
-            break;
-        }
-        let cmd = line.trim();
-        let mut parts = cmd.split_whitespace();
-        // Register the sender for the user after REGISTER
-        match parts.clone().next() {
-            Some("REGISTER") => {
-                if let Some(name) = parts.clone().nth(1) {
-                    let mut state = state.lock().unwrap();
-                    state.writers.insert(name.to_string(), tx.clone());
-                }
+        let line = match pending.take() {
+            Some(line) => line,
+            None => match next_line(&mut reader).await {
+                Some(line) => line,
+                None => break,
             },
-            _ => {}
-        }
-        // Show cards to the player if they have an active game and hole_cards
-        match parts.clone().next() {
-            Some("REGISTER") | Some("CREATE_TABLE") | Some("LIST_TABLES") | Some("QUIT") => {},
-            _ => {
-                let (mut cards, mut pot, mut comm_cards) = (None, 0.0, vec![]);
-                'outer: {
-                    let state = state.lock().unwrap();
-                    if let Some(ref uid) = user_id {
-                        for table in state.tables.values() {
-                            if let Some(game) = &table.game {
-                                for player in &game.players {
-                                    if player.name == *uid && !player.hole_cards.is_empty() {
-                                        cards = Some(player.hole_cards.clone());
-                                        pot = game.get_pot();
-                                        comm_cards = game.get_community_cards().to_vec();
-                                        break 'outer;
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-                if let Some(cards) = cards {
-                    let _ = tx.send(format!(
-                        "Your cards: {:?}\nPot: {}\nCommunity cards: {:?}\n",
-                        cards, pot, comm_cards
-                    ).to_string());
-                }
+        };
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                let _ = tx.send(ServerMessage::error(e));
+                continue;
             }
+        };
+        if !handle_line(protocol, &line, &mut user_id, &state, &tx).await {
+            break;
         }
-        match parts.next() {
-            Some("REGISTER") => {
-                if let Some(name) = parts.next() {
-                    handle_register(name, &state, &tx, &mut user_id);
-                } else {
-                    let _ = tx.send("Usage: REGISTER <name>\n".to_string());
-                }
+    }
+    handle_disconnect(&user_id, &state, &tx).await;
+}
+
+/// Reads the next line from a client. A line that is not valid UTF-8 or is longer
+/// than `MAX_LINE` is an error to send back; None once the connection is closed or broken.
+async fn next_line(reader: &mut BufReader<OwnedReadHalf>) -> Option<Result<String, String>> {
+    let mut bytes = Vec::new();
+    match (&mut *reader).take(MAX_LINE as u64).read_until(b'\n', &mut bytes).await {
+        Ok(0) | Err(_) => return None,
+        Ok(_) => {}
+    }
+    if bytes.len() == MAX_LINE && bytes.last() != Some(&b'\n') {
+        // Skip the rest of the line without holding it in memory
+        loop {
+            let buf = reader.fill_buf().await.ok()?;
+            if buf.is_empty() {
+                return None;
             }
-            Some("CREATE_TABLE") => {
-                if let Some(table) = parts.next() {
-                    handle_create_table(table, &state, &tx);
-                } else {
-                    let _ = tx.send("Usage: CREATE_TABLE <table>\n".to_string());
+            match buf.iter().position(|&b| b == b'\n') {
+                Some(end) => {
+                    reader.consume(end + 1);
+                    break;
                 }
-            }
-            Some("JOIN_TABLE") => {
-                if let Some(table) = parts.next() {
-                    handle_join_table(&user_id, table, &state, &tx);
-                } else {
-                    let _ = tx.send("Usage: JOIN_TABLE <table>\n".to_string());
+                None => {
+                    let len = buf.len();
+                    reader.consume(len);
                 }
             }
-            Some("LIST_TABLES") => {
-                handle_list_tables(&state, &tx);
-            }
-            Some("SHOW") => {
-                handle_show(&user_id, &state, &tx).await;
-            }
-            Some("QUIT") => {
-                handle_quit(&tx).await;
-                break;
+        }
+        return Some(Err(format!("Lines can be at most {} bytes", MAX_LINE)));
+    }
+    Some(String::from_utf8(bytes).map_err(|_| "Commands must be valid UTF-8 text".to_string()))
+}
+
+/// Confirms the protocol a connection picked in its handshake
+pub fn acknowledge_protocol(protocol: Protocol, tx: &UnboundedSender<ServerMessage>) {
+    let name = if protocol == Protocol::Json { "JSON" } else { "text" };
+    let _ = tx.send(ServerMessage::ok(format!("Using the {} protocol", name)));
+}
+
+/// Parses and handles one message from a client, whichever transport it came over.
+/// Returns false once the client has quit.
+pub async fn handle_line(protocol: Protocol, line: &str, user_id: &mut Option<UserId>, state: &Arc<Mutex<ServerState>>, tx: &UnboundedSender<ServerMessage>) -> bool {
+    let message = match protocol.parse(line) {
+        Ok(Some(message)) => message,
+        Ok(None) => return true,
+        Err(e) => {
+            let _ = tx.send(ServerMessage::error(e));
+            return true;
+        }
+    };
+    // A connection whose session was resumed elsewhere no longer acts for the user
+    if let Some(uid) = user_id.as_ref() {
+        let taken_over = {
+            let state = state.lock().unwrap();
+            state.writers.get(uid).is_some_and(|writer| !writer.same_channel(tx))
+        };
+        if taken_over {
+            *user_id = None;
+            let _ = tx.send(ServerMessage::error("Your session was resumed on another connection"));
+            return true;
+        }
+    }
+    // Remind a seated player of their cards, as they see them, before handling a command
+    match message {
+        ClientMessage::Register { .. } | ClientMessage::Login { .. } | ClientMessage::ChangePassword { .. } | ClientMessage::Resume { .. } | ClientMessage::CreateTable { .. } | ClientMessage::ListTables | ClientMessage::Quit => {},
+        _ => {
+            let hand = user_id.as_ref().and_then(|uid| {
+                let state = state.lock().unwrap();
+                let game = state.users.get(uid)?
+                    .table.as_ref()
+                    .and_then(|t| state.tables.get(t))?
+                    .game.as_ref()?;
+                let view = PlayerView::new(game, uid);
+                let cards = view.own_cards();
+                (!cards.is_empty()).then_some(ServerMessage::Hand { cards, pot: view.pot, community_cards: view.community_cards })
+            });
+            if let Some(hand) = hand {
+                let _ = tx.send(hand);
             }
-            Some("BET") => {
-                if let Some(amount_str) = parts.next() {
-                    let amount: f64 = amount_str.parse().unwrap_or(0.0);
-                    handle_bet(&user_id, amount, &state, &tx).await;
-                } else {
-                    let _ = tx.send("Usage: BET <amount>\n".to_string());
+        }
+    }
+    match message {
+        ClientMessage::Register { name, password } => {
+            handle_register(&name, &password, state, tx, user_id).await;
+        }
+        ClientMessage::Login { name, password } => {
+            handle_login(&name, &password, state, tx, user_id).await;
+        }
+        ClientMessage::ChangePassword { old, new } => {
+            handle_change_password(user_id, &old, &new, state, tx).await;
+        }
+        ClientMessage::Resume { token } => {
+            handle_resume(&token, state, tx, user_id).await;
+        }
+        ClientMessage::CreateTable { table, variant, options } => {
+            let variant = match variant {
+                Some(name) => parse_variant(&name).ok_or_else(|| format!("Unknown variant: {}", name)),
+                None => Ok(default_variant()),
+            };
+            // Tables start with the server's clock, which their options can change
+            let clock = state.lock().unwrap().config.clock_rules();
+            let mut settings = TableSettings { clock, ..TableSettings::default() };
+            let setup = variant.and_then(|variant| {
+                options.iter().try_for_each(|(key, value)| settings.set(key, value)).map(|_| variant)
+            });
+            match setup {
+                Ok(variant) => handle_create_table(user_id, &table, variant, settings, state, tx),
+                Err(e) => {
+                    let _ = tx.send(ServerMessage::error(e));
                 }
             }
-            Some("CALL") => {
-                handle_call(&user_id, &state, &tx).await;
-            }
-            Some("CHECK") => {
-                handle_check(&user_id, &state, &tx).await;
-            }
-            Some("FOLD") => {
-                handle_fold(&user_id, &state, &tx).await;
-            }
-            Some("SHOW_STATE") => {
-                handle_show_state(&state, &user_id, &tx).await;
-            }
-            Some(cmd) => {
-                let _ = tx.send(format!("Unknown command: {}\n", cmd).to_string());
-            }
-            None => {}
         }
+        ClientMessage::JoinTable { table } => {
+            handle_join_table(user_id, &table, state, tx).await;
+        }
+        ClientMessage::WatchTable { table } => {
+            handle_watch_table(user_id, &table, state, tx).await;
+        }
+        ClientMessage::Unwatch => {
+            handle_unwatch(user_id, state, tx);
+        }
+        ClientMessage::ListTables => {
+            handle_list_tables(state, tx);
+        }
+        ClientMessage::Show => {
+            handle_show(user_id, state, tx).await;
+        }
+        ClientMessage::Quit => {
+            handle_quit(tx).await;
+            return false;
+        }
+        ClientMessage::Bet { amount } => {
+            handle_bet(user_id, amount, state, tx).await;
+        }
+        ClientMessage::Call => {
+            handle_call(user_id, state, tx).await;
+        }
+        ClientMessage::Check => {
+            handle_check(user_id, state, tx).await;
+        }
+        ClientMessage::AllIn => {
+            handle_all_in(user_id, state, tx).await;
+        }
+        ClientMessage::Fold => {
+            handle_fold(user_id, state, tx).await;
+        }
+        ClientMessage::Draw { cards } => {
+            handle_draw(user_id, cards, state, tx).await;
+        }
+        ClientMessage::Discard { card } => {
+            handle_discard(user_id, card, state, tx).await;
+        }
+        ClientMessage::Run { times } => {
+            handle_run(user_id, times, state, tx).await;
+        }
+        ClientMessage::BombPot { double_board } => {
+            handle_bomb_pot(user_id, double_board, state, tx);
+        }
+        ClientMessage::ShowHand => {
+            handle_show_or_muck(user_id, true, state, tx).await;
+        }
+        ClientMessage::Muck => {
+            handle_show_or_muck(user_id, false, state, tx).await;
+        }
+        ClientMessage::ShowCards { cards } => {
+            handle_show_cards(user_id, cards, state, tx);
+        }
+        ClientMessage::AutoMuck { on } => {
+            handle_auto_muck(user_id, on, state, tx);
+        }
+        ClientMessage::Rabbit => {
+            handle_rabbit(user_id, state, tx);
+        }
+        ClientMessage::History => {
+            handle_history(user_id, state, tx);
+        }
+        ClientMessage::Say { text } => {
+            handle_say(user_id, &text, state, tx);
+        }
+        ClientMessage::Whisper { to, text } => {
+            handle_whisper(user_id, &to, &text, state, tx);
+        }
+        ClientMessage::Mute { user } => {
+            handle_mute(user_id, &user, true, state, tx);
+        }
+        ClientMessage::Unmute { user } => {
+            handle_mute(user_id, &user, false, state, tx);
+        }
+        ClientMessage::ShowState => {
+            handle_show_state(state, user_id, tx).await;
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_bad_input_gets_an_error_instead_of_ending_the_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let state = Arc::new(Mutex::new(ServerState::default()));
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            handle_client(socket, state).await;
+        });
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        for banner in BANNER {
+            assert_eq!(lines.next_line().await.unwrap().unwrap(), banner);
+        }
+        writer.write_all(b"PROTOCOL text\nSAY \xff\xfe\nLIST_TABLES\n").await.unwrap();
+        assert_eq!(lines.next_line().await.unwrap().unwrap(), "Using the text protocol");
+        assert_eq!(lines.next_line().await.unwrap().unwrap(), "Commands must be valid UTF-8 text");
+        assert_eq!(lines.next_line().await.unwrap().unwrap(), "Tables: ");
+    }
+
+    #[tokio::test]
+    async fn test_overlong_line_is_skipped_with_an_error() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let state = Arc::new(Mutex::new(ServerState::default()));
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            handle_client(socket, state).await;
+        });
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        for banner in BANNER {
+            assert_eq!(lines.next_line().await.unwrap().unwrap(), banner);
+        }
+        writer.write_all(b"PROTOCOL text\n").await.unwrap();
+        assert_eq!(lines.next_line().await.unwrap().unwrap(), "Using the text protocol");
+        let overlong = format!("SAY {}\nLIST_TABLES\n", "a".repeat(MAX_LINE * 4));
+        writer.write_all(overlong.as_bytes()).await.unwrap();
+        assert_eq!(lines.next_line().await.unwrap().unwrap(), format!("Lines can be at most {} bytes", MAX_LINE));
+        // The rest of the long line is dropped, not read as more commands
+        assert_eq!(lines.next_line().await.unwrap().unwrap(), "Tables: ");
     }
 }
@@ -1,14 +1,28 @@
 use crate::card::Card;
+use serde::Serialize;
+use std::time::Instant;
 
 #[derive(Debug, Clone)]
 pub struct Player {
     pub name: String,
     pub balance: f64,
     pub hole_cards: Vec<Card>,
+    /// Cards dealt face up (stud), visible to the whole table
+    pub up_cards: Vec<Card>,
     pub hand_strength: f64,
     pub chips_in_play: f64,
     pub state: PlayerState,
     pub action: Option<PlayerAction>,
+    /// Whether a losing hand is mucked at showdown instead of shown
+    pub auto_muck: bool,
+    /// Seconds of extra thinking time left once the action clock runs out
+    pub time_bank: u64,
+    /// When the player lost their connection to the server, if they are away
+    pub disconnected_at: Option<Instant>,
+    /// Hands dealt to the player since they disconnected
+    pub missed_hands: usize,
+    /// Left out of new hands until the player comes back, or because they have no chips
+    pub sitting_out: bool,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -18,9 +32,14 @@ pub enum PlayerAction {
     Call,
     Raise(f64),
     AllIn,
+    /// Throw away the given hole cards and draw replacements (draw poker)
+    Draw(Vec<Card>),
+    /// Throw away one hole card without a replacement (Pineapple)
+    Discard(Card),
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum PlayerState {
     Active,
     Folded,
@@ -34,10 +53,16 @@ impl Player {
             name,
             balance,
             hole_cards: Vec::new(),
+            up_cards: Vec::new(),
             hand_strength: 0.0,
             chips_in_play: 0.0,
             state: PlayerState::Active,
             action: None,
+            auto_muck: true,
+            time_bank: 0,
+            disconnected_at: None,
+            missed_hands: 0,
+            sitting_out: false,
         }
     }
 
@@ -45,8 +70,20 @@ impl Player {
         self.hole_cards.push(card);
     }
 
+    pub fn add_up_card(&mut self, card: Card) {
+        self.up_cards.push(card);
+    }
+
+    /// Face-down and face-up cards together
+    pub fn all_cards(&self) -> Vec<Card> {
+        let mut cards = self.hole_cards.clone();
+        cards.extend_from_slice(&self.up_cards);
+        cards
+    }
+
     pub fn clear_cards(&mut self) {
         self.hole_cards.clear();
+        self.up_cards.clear();
     }
 
     pub fn bet(&mut self, amount: f64) -> Result<f64, String> {
@@ -92,6 +129,19 @@ impl Player {
         amount
     }
 
+    /// Replaces the discarded hole cards with the newly drawn ones
+    pub fn draw(&mut self, discards: &[Card], new_cards: Vec<Card>) {
+        self.hole_cards.retain(|c| !discards.contains(c));
+        self.hole_cards.extend(new_cards);
+        self.action = Some(PlayerAction::Draw(discards.to_vec()));
+    }
+
+    /// Throws away one hole card
+    pub fn discard(&mut self, card: Card) {
+        self.hole_cards.retain(|c| *c != card);
+        self.action = Some(PlayerAction::Discard(card));
+    }
+
     pub fn collect_winnings(&mut self, amount: f64) {
         self.balance += amount;
         self.chips_in_play = 0.0;
@@ -101,7 +151,7 @@ impl Player {
         self.clear_cards();
         self.hand_strength = 0.0;
         self.chips_in_play = 0.0;
-        self.state = PlayerState::Active;
+        self.state = if self.sitting_out { PlayerState::SittingOut } else { PlayerState::Active };
         self.action = None;
     }
 }
\ No newline at end of file
@@ -0,0 +1,637 @@
+use crate::card::Card;
+use crate::history::{self, ActionTaken, EventView};
+use crate::player::PlayerState;
+use crate::view::PlayerView;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Commands listed to players along with their game state
+const COMMANDS: &str = "Available commands: BET <amount>, CALL, CHECK, ALL_IN, FOLD, DRAW [cards], DISCARD <card>, RUN <times>, SHOW_HAND, MUCK, RABBIT, BOMB_POT [double], AUTO_MUCK <on|off>, SHOW_CARDS [cards], SAY <text>, WHISPER <user> <text>, MUTE <user>, UNMUTE <user>, HISTORY, SHOW, SHOW_STATE, QUIT";
+
+/// How a connection talks to the server, chosen with `PROTOCOL <text|json>` as its first line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    /// Whitespace-separated commands and human-readable replies
+    Text,
+    /// One JSON object per line in both directions
+    Json,
+}
+
+impl Protocol {
+    /// Reads the handshake line, if that is what the line is
+    pub fn from_handshake(line: &str) -> Option<Protocol> {
+        let mut parts = line.split_whitespace();
+        if !parts.next()?.eq_ignore_ascii_case("PROTOCOL") {
+            return None;
+        }
+        match parts.next()?.to_ascii_lowercase().as_str() {
+            "text" => Some(Protocol::Text),
+            "json" => Some(Protocol::Json),
+            _ => None,
+        }
+    }
+
+    /// Parses one line from a client
+    pub fn parse(&self, line: &str) -> Result<Option<ClientMessage>, String> {
+        match self {
+            Protocol::Text => ClientMessage::parse_text(line),
+            Protocol::Json if line.trim().is_empty() => Ok(None),
+            Protocol::Json => serde_json::from_str(line).map(Some).map_err(|e| format!("Invalid message: {}", e)),
+        }
+    }
+
+    /// Renders one message for a client, ending in a newline
+    pub fn render(&self, message: &ServerMessage) -> String {
+        match self {
+            Protocol::Text => message.to_text(),
+            Protocol::Json => {
+                let mut line = serde_json::to_string(message).unwrap_or_default();
+                line.push('\n');
+                line
+            }
+        }
+    }
+}
+
+/// Everything a client can ask of the server
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Register { name: String, password: String },
+    Login { name: String, password: String },
+    ChangePassword { old: String, new: String },
+    Resume { token: String },
+    /// Creates a table; options are the same `key=value` settings as the text command
+    CreateTable {
+        table: String,
+        #[serde(default)]
+        variant: Option<String>,
+        #[serde(default)]
+        options: BTreeMap<String, String>,
+    },
+    JoinTable { table: String },
+    WatchTable { table: String },
+    Unwatch,
+    ListTables,
+    Show,
+    Quit,
+    Bet { amount: f64 },
+    Call,
+    Check,
+    AllIn,
+    Fold,
+    /// Cards to throw away in a draw; none means standing pat
+    Draw {
+        #[serde(default)]
+        cards: Vec<Card>,
+    },
+    Discard { card: Card },
+    Run { times: usize },
+    BombPot {
+        #[serde(default)]
+        double_board: bool,
+    },
+    /// Turn the hand over at showdown, when it is the player's turn to show or muck
+    ShowHand,
+    /// Throw the losing hand away unseen at showdown
+    Muck,
+    /// Hole cards to show from the last hand; none means all of them
+    ShowCards {
+        #[serde(default)]
+        cards: Vec<Card>,
+    },
+    AutoMuck { on: bool },
+    Rabbit,
+    History,
+    ShowState,
+    Say { text: String },
+    Whisper { to: String, text: String },
+    Mute { user: String },
+    Unmute { user: String },
+}
+
+impl ClientMessage {
+    /// Parses a text command such as `BET 10` or `DRAW As 7d`. A blank line is no
+    /// command at all; a bad one gives the error or usage to send back.
+    pub fn parse_text(line: &str) -> Result<Option<ClientMessage>, String> {
+        let mut parts = line.split_whitespace();
+        let Some(cmd) = parts.next() else {
+            return Ok(None);
+        };
+        let message = match cmd {
+            "REGISTER" | "LOGIN" => {
+                let usage = || format!("Usage: {} <name> <password>", cmd);
+                let name = parts.next().ok_or_else(usage)?.to_string();
+                let password = parts.next().ok_or_else(usage)?.to_string();
+                if cmd == "REGISTER" {
+                    ClientMessage::Register { name, password }
+                } else {
+                    ClientMessage::Login { name, password }
+                }
+            }
+            "CHANGE_PASSWORD" => {
+                let usage = "Usage: CHANGE_PASSWORD <old password> <new password>";
+                ClientMessage::ChangePassword {
+                    old: parts.next().ok_or(usage)?.to_string(),
+                    new: parts.next().ok_or(usage)?.to_string(),
+                }
+            }
+            "CREATE_TABLE" => {
+                let table = parts.next().ok_or("Usage: CREATE_TABLE <table> [holdem|omaha8|shortdeck|shortdeck-classic|stud|razz|draw|27td|pineapple|crazy-pineapple][+joker|+jokers|+deuces] [rabbit=<hunts per hand>] [bomb_every=<hands>] [bomb_ante=<chips>] [bomb_double=<true|false>] [all_in_face_up=<true|false>] [action_time=<seconds>] [time_bank=<seconds>] [time_bank_refill=<seconds per hand>] [disconnect_grace=<seconds>] [sit_out_after=<hands>] [max_spectators=<count>]")?;
+                // Optional variant name and key=value table options, in any order
+                let mut variant = None;
+                let mut options = BTreeMap::new();
+                for arg in parts.by_ref() {
+                    match arg.split_once('=') {
+                        Some((key, value)) => {
+                            options.insert(key.to_string(), value.to_string());
+                        }
+                        None => variant = Some(arg.to_string()),
+                    }
+                }
+                ClientMessage::CreateTable { table: table.to_string(), variant, options }
+            }
+            "RESUME" => ClientMessage::Resume {
+                token: parts.next().ok_or("Usage: RESUME <token>")?.to_string(),
+            },
+            "JOIN_TABLE" => ClientMessage::JoinTable {
+                table: parts.next().ok_or("Usage: JOIN_TABLE <table>")?.to_string(),
+            },
+            "WATCH_TABLE" => ClientMessage::WatchTable {
+                table: parts.next().ok_or("Usage: WATCH_TABLE <table>")?.to_string(),
+            },
+            "UNWATCH" => ClientMessage::Unwatch,
+            "LIST_TABLES" => ClientMessage::ListTables,
+            "SHOW" => ClientMessage::Show,
+            "QUIT" => ClientMessage::Quit,
+            "BET" => ClientMessage::Bet {
+                amount: parts.next()
+                    .and_then(|n| n.parse::<f64>().ok())
+                    .filter(|amount| amount.is_finite() && *amount > 0.0)
+                    .ok_or("Usage: BET <amount>, a positive number of chips")?,
+            },
+            "CALL" => ClientMessage::Call,
+            "CHECK" => ClientMessage::Check,
+            "ALL_IN" => ClientMessage::AllIn,
+            "FOLD" => ClientMessage::Fold,
+            "DRAW" => ClientMessage::Draw {
+                cards: parse_cards(parts).map_err(|e| format!("{}\nUsage: DRAW [card...] e.g. DRAW As 7d", e))?,
+            },
+            "DISCARD" => ClientMessage::Discard {
+                card: parts.next().ok_or("Usage: DISCARD <card>")?.parse()?,
+            },
+            "RUN" => ClientMessage::Run {
+                times: parts.next().and_then(|n| n.parse().ok()).ok_or("Usage: RUN <times>")?,
+            },
+            "BOMB_POT" => match parts.next() {
+                None => ClientMessage::BombPot { double_board: false },
+                Some(arg) if arg.eq_ignore_ascii_case("double") => ClientMessage::BombPot { double_board: true },
+                Some(_) => return Err("Usage: BOMB_POT [double]".to_string()),
+            },
+            "SHOW_HAND" => ClientMessage::ShowHand,
+            "MUCK" => ClientMessage::Muck,
+            "SHOW_CARDS" => ClientMessage::ShowCards {
+                cards: parse_cards(parts).map_err(|e| format!("{}\nUsage: SHOW_CARDS [card...] e.g. SHOW_CARDS Ah", e))?,
+            },
+            "AUTO_MUCK" => match parts.next().map(|s| s.to_ascii_lowercase()).as_deref() {
+                Some("on") => ClientMessage::AutoMuck { on: true },
+                Some("off") => ClientMessage::AutoMuck { on: false },
+                _ => return Err("Usage: AUTO_MUCK <on|off>".to_string()),
+            },
+            "RABBIT" => ClientMessage::Rabbit,
+            "HISTORY" => ClientMessage::History,
+            "SHOW_STATE" => ClientMessage::ShowState,
+            // Chat keeps the rest of the line as typed, spacing included
+            "SAY" => ClientMessage::Say {
+                text: rest_of_line(line, 1).ok_or("Usage: SAY <text>")?.to_string(),
+            },
+            "WHISPER" => ClientMessage::Whisper {
+                to: parts.next().ok_or("Usage: WHISPER <user> <text>")?.to_string(),
+                text: rest_of_line(line, 2).ok_or("Usage: WHISPER <user> <text>")?.to_string(),
+            },
+            "MUTE" => ClientMessage::Mute {
+                user: parts.next().ok_or("Usage: MUTE <user>")?.to_string(),
+            },
+            "UNMUTE" => ClientMessage::Unmute {
+                user: parts.next().ok_or("Usage: UNMUTE <user>")?.to_string(),
+            },
+            cmd => return Err(format!("Unknown command: {}", cmd)),
+        };
+        Ok(Some(message))
+    }
+}
+
+/// What follows the first `words` words of a line, if anything does
+fn rest_of_line(line: &str, words: usize) -> Option<&str> {
+    let mut rest = line.trim();
+    for _ in 0..words {
+        rest = rest.split_once(char::is_whitespace)?.1.trim_start();
+    }
+    (!rest.is_empty()).then_some(rest)
+}
+
+fn parse_cards<'a>(parts: impl Iterator<Item = &'a str>) -> Result<Vec<Card>, String> {
+    parts.map(|c| c.parse::<Card>()).collect()
+}
+
+/// Everything the server sends to a client
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    /// A command succeeded
+    Ok { message: String },
+    /// A command failed or was not understood
+    Error { message: String },
+    /// The token lets a later connection take over the account with RESUME
+    Registered { name: String, balance: f64, token: String },
+    /// A new connection took over the account; the game state follows if they are at a table
+    Resumed { name: String, balance: f64, table: Option<String> },
+    /// As `Resumed`, after a password login, with a new session token
+    LoggedIn { name: String, balance: f64, token: String, table: Option<String> },
+    /// The password was changed; earlier session tokens no longer work, this one does
+    PasswordChanged { token: String },
+    TableCreated { table: String, variant: String },
+    Joined { table: String },
+    Watching { table: String },
+    Tables { tables: Vec<String> },
+    /// The user's account and, if they are seated, their table
+    Profile { name: String, balance: f64, table: Option<TableSummary> },
+    /// The user's cards, sent as a reminder before a command is handled
+    Hand { cards: Vec<Card>, pot: f64, community_cards: Vec<Card> },
+    /// The game as the user sees it, sent after every action
+    State(GameState),
+    /// The last completed hand, as the user is allowed to see it
+    History { events: Vec<EventView> },
+    /// Something that happened at the table, sent to everyone seated there
+    Event(TableEvent),
+    /// A chat message from another user, or the user's own table or lobby message
+    Chat { from: String, scope: ChatScope, text: String },
+    Bye,
+}
+
+/// Who a chat message was sent to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatScope {
+    /// Everyone at the sender's table, players and spectators
+    Table,
+    /// Everyone not at a table
+    Lobby,
+    /// Only the receiver
+    Whisper,
+}
+
+/// A table as shown by SHOW
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TableSummary {
+    pub table: String,
+    pub players: Vec<String>,
+    pub game: Option<PlayerView>,
+}
+
+/// A player's view of the game in progress
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GameState {
+    #[serde(flatten)]
+    pub view: PlayerView,
+    pub your_turn: bool,
+    /// Seconds to act and time bank left, when it is the player's turn
+    pub time_left: Option<(u64, u64)>,
+    pub round_ended: bool,
+}
+
+/// Something that happened at a table
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum TableEvent {
+    /// Another player acted
+    Acted { player: String, action: ActionTaken },
+    /// A hand was settled
+    Showdown(Showdown),
+    ShowsCards { player: String, cards: Vec<Card> },
+    BombPotCalled { host: String, double_board: bool },
+    RabbitHunt { player: String, cards: Vec<Card> },
+    /// The player whose turn it is is running out of time
+    ClockWarning { player: String, seconds: u64 },
+    TimeBankStarted { player: String, seconds: u64 },
+    TimeBankWarning { player: String, seconds: u64 },
+    /// A player ran out of time and the game acted for them
+    TimedOut { player: String, action: ActionTaken, showdowns: Vec<Showdown>, next_player: Option<String> },
+    /// A player lost their connection; the game acts for them after a grace period
+    Disconnected { player: String },
+    Reconnected { player: String },
+}
+
+/// How a hand was settled: the hands shown, the boards and who won each pot
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Showdown {
+    pub reveals: Vec<RevealedHand>,
+    /// One board per run, or per board in a double-board bomb pot
+    pub boards: Vec<Vec<Card>>,
+    pub double_board: bool,
+    pub pots: Vec<PotWon>,
+}
+
+/// A player's hand at showdown; `cards` is `None` when they mucked
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RevealedHand {
+    pub player: String,
+    pub cards: Option<Vec<Card>>,
+}
+
+/// One pot, or one run's share of it, and who it went to
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PotWon {
+    pub amount: f64,
+    /// Index into `boards` of the board the pot was played on
+    pub run: usize,
+    pub high_winners: Vec<String>,
+    pub low_winners: Vec<String>,
+    pub payouts: Vec<Payout>,
+}
+
+/// Chips paid to one winner; `hand` names their hand when it was contested
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Payout {
+    pub player: String,
+    pub amount: f64,
+    pub hand: Option<String>,
+}
+
+impl ServerMessage {
+    pub fn ok(message: impl Into<String>) -> Self {
+        ServerMessage::Ok { message: message.into() }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        ServerMessage::Error { message: message.into() }
+    }
+
+    /// Renders the message for a text-protocol client
+    pub fn to_text(&self) -> String {
+        match self {
+            ServerMessage::Ok { message } | ServerMessage::Error { message } => format!("{}\n", message),
+            ServerMessage::Registered { balance, token, .. } => {
+                format!("Registered successfully. Your balance: {}\nSession token: {}\n", balance, token)
+            }
+            ServerMessage::Resumed { name, balance, table } => {
+                let mut out = format!("Welcome back, {}. Your balance: {}\n", name, balance);
+                if let Some(table) = table {
+                    out.push_str(&format!("Back at table {}\n", table));
+                }
+                out
+            }
+            ServerMessage::LoggedIn { name, balance, token, table } => {
+                let mut out = format!("Welcome back, {}. Your balance: {}\nSession token: {}\n", name, balance, token);
+                if let Some(table) = table {
+                    out.push_str(&format!("Back at table {}\n", table));
+                }
+                out
+            }
+            ServerMessage::PasswordChanged { token } => format!("Password changed\nSession token: {}\n", token),
+            ServerMessage::TableCreated { variant, .. } => format!("Table created ({})\n", variant),
+            ServerMessage::Joined { .. } => "Joined table\n".to_string(),
+            ServerMessage::Watching { table } => format!("Watching table {}\n", table),
+            ServerMessage::Tables { tables } => format!("Tables: {}\n", tables.join(", ")),
+            ServerMessage::Profile { name, balance, table } => {
+                let mut out = format!("You: {} | Balance: {}\n", name, balance);
+                if let Some(table) = table {
+                    out.push_str(&format!("Table: {}\nPlayers: {:?}\n", table.table, table.players));
+                    if let Some(game) = &table.game {
+                        out.push_str(&format!("Pot: {}\nCommunity cards: {:?}\n", game.pot, game.community_cards));
+                        for (i, seat) in game.seats.iter().enumerate() {
+                            let cards = match &seat.hole_cards {
+                                Some(cards) => format!("{:?}", cards),
+                                None => format!("{} hidden", seat.hidden_cards),
+                            };
+                            out.push_str(&format!("Player {}: {} | Cards: {} | Up: {:?} | Balance: {}\n", i, seat.name, cards, seat.up_cards, seat.balance));
+                        }
+                    }
+                }
+                out
+            }
+            ServerMessage::Hand { cards, pot, community_cards } => {
+                format!("Your cards: {}\nPot: {}\nCommunity cards: {}\n", join_cards(cards), pot, join_cards(community_cards))
+            }
+            ServerMessage::State(state) => state.to_text(),
+            ServerMessage::History { events } => format!("Last hand:\n{}", history::render(events)),
+            ServerMessage::Event(event) => event.to_text(),
+            ServerMessage::Chat { from, scope, text } => {
+                let scope = match scope {
+                    ChatScope::Table => "table",
+                    ChatScope::Lobby => "lobby",
+                    ChatScope::Whisper => "whisper",
+                };
+                format!("[{}] {}: {}\n", scope, from, text)
+            }
+            ServerMessage::Bye => "Bye!\n".to_string(),
+        }
+    }
+}
+
+impl GameState {
+    fn to_text(&self) -> String {
+        let view = &self.view;
+        let mut out = format!("Your cards: {:?}\nPot: {}\nCommunity cards: {:?}\n", view.own_cards(), view.pot, view.community_cards);
+        // The community cards are the first board; a double-board bomb pot has a second one
+        for (i, board) in view.boards.iter().enumerate().skip(1) {
+            out.push_str(&format!("Board {}: {:?}\n", i + 1, board));
+        }
+        out.push_str(&format!("{}\n", COMMANDS));
+        if let Some(cp) = &view.current_player {
+            out.push_str(&format!("Current player: {}{}\n", cp, if self.your_turn { " (you)" } else { "" }));
+            if self.your_turn
+                && let Some((action_secs, time_bank)) = self.time_left
+            {
+                out.push_str(&format!("Your turn! You have {} seconds, plus {} in your time bank\n", action_secs, time_bank));
+            }
+        }
+        if view.own_seat().is_some_and(|s| s.state == PlayerState::Folded) {
+            out.push_str("You have folded\n");
+        }
+        if self.round_ended {
+            out.push_str("Betting round ended, next round started\n");
+        }
+        out
+    }
+}
+
+impl TableEvent {
+    fn to_text(&self) -> String {
+        match self {
+            TableEvent::Acted { player, action } => format!("{} {}\n", player, action),
+            TableEvent::Showdown(showdown) => format!("Showdown: {}\n", showdown.to_text()),
+            TableEvent::ShowsCards { player, cards } => format!("{} shows {}\n", player, join_cards(cards)),
+            TableEvent::BombPotCalled { host, double_board } => {
+                let kind = if *double_board { "double-board bomb pot" } else { "bomb pot" };
+                format!("{} called a {} for the next hand\n", host, kind)
+            }
+            TableEvent::RabbitHunt { player, cards } => format!("{} rabbit hunts: {}\n", player, join_cards(cards)),
+            TableEvent::ClockWarning { player, seconds } => format!("{} has {} seconds to act\n", player, seconds),
+            TableEvent::TimeBankStarted { player, seconds } => {
+                format!("{} is using their time bank ({} seconds left)\n", player, seconds)
+            }
+            TableEvent::TimeBankWarning { player, seconds } => format!("{} has {} seconds of time bank left\n", player, seconds),
+            TableEvent::Disconnected { player } => format!("{} disconnected\n", player),
+            TableEvent::Reconnected { player } => format!("{} reconnected\n", player),
+            TableEvent::TimedOut { player, action, showdowns, next_player } => {
+                let mut out = format!("{} ran out of time and {}\n", player, action);
+                for showdown in showdowns {
+                    out.push_str(&format!("Showdown: {}\n", showdown.to_text()));
+                }
+                if let Some(next) = next_player {
+                    out.push_str(&format!("Current player: {}\n", next));
+                }
+                out
+            }
+        }
+    }
+}
+
+impl Showdown {
+    /// Lists the shown hands, the boards and the winners on one line
+    fn to_text(&self) -> String {
+        let mut parts = Vec::new();
+        for reveal in &self.reveals {
+            match &reveal.cards {
+                Some(cards) => parts.push(format!("{} shows {}", reveal.player, join_cards(cards))),
+                None => parts.push(format!("{} mucks", reveal.player)),
+            }
+        }
+        for (run, board) in self.boards.iter().enumerate() {
+            if self.double_board {
+                parts.push(format!("Board {}: {}", run + 1, join_cards(board)));
+            } else {
+                parts.push(format!("Run {} board: {}", run + 1, join_cards(board)));
+            }
+        }
+        for pot in &self.pots {
+            let on_run = match self.boards.len() {
+                0 | 1 => String::new(),
+                _ if self.double_board => format!(" on board {}", pot.run + 1),
+                _ => format!(" on run {}", pot.run + 1),
+            };
+            for payout in &pot.payouts {
+                let half = match (pot.high_winners.contains(&payout.player), pot.low_winners.contains(&payout.player)) {
+                    (true, true) => " (high and low)",
+                    (true, false) if !pot.low_winners.is_empty() => " (high)",
+                    (false, true) if !pot.high_winners.is_empty() => " (low)",
+                    _ => "",
+                };
+                match &payout.hand {
+                    Some(hand) => parts.push(format!("{} wins {} with {}{}{}", payout.player, payout.amount, hand, half, on_run)),
+                    None => parts.push(format!("{} wins {}{}{}", payout.player, payout.amount, half, on_run)),
+                }
+            }
+        }
+        parts.join(", ")
+    }
+}
+
+fn join_cards(cards: &[Card]) -> String {
+    cards.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::{Rank, Suit};
+
+    #[test]
+    fn test_text_and_json_commands_parse_the_same() {
+        let text = ClientMessage::parse_text("DRAW As 7d").unwrap().unwrap();
+        let json = Protocol::Json.parse(r#"{"type":"draw","cards":["As","7d"]}"#).unwrap().unwrap();
+        assert_eq!(text, json);
+        assert_eq!(
+            ClientMessage::parse_text("CREATE_TABLE t1 rabbit=2 razz").unwrap().unwrap(),
+            ClientMessage::CreateTable {
+                table: "t1".to_string(),
+                variant: Some("razz".to_string()),
+                options: BTreeMap::from([("rabbit".to_string(), "2".to_string())]),
+            }
+        );
+        assert_eq!(
+            ClientMessage::parse_text("LOGIN alice s3cret-pw").unwrap().unwrap(),
+            ClientMessage::Login { name: "alice".to_string(), password: "s3cret-pw".to_string() }
+        );
+        assert_eq!(ClientMessage::parse_text("REGISTER alice").unwrap_err(), "Usage: REGISTER <name> <password>");
+        assert_eq!(ClientMessage::parse_text("  ").unwrap(), None);
+        assert_eq!(ClientMessage::parse_text("RUN").unwrap_err(), "Usage: RUN <times>");
+        assert_eq!(ClientMessage::parse_text("BET 2.5").unwrap().unwrap(), ClientMessage::Bet { amount: 2.5 });
+        for bad in ["BET", "BET abc", "BET -5", "BET 0", "BET NaN", "BET inf"] {
+            assert_eq!(ClientMessage::parse_text(bad).unwrap_err(), "Usage: BET <amount>, a positive number of chips");
+        }
+        assert_eq!(
+            ClientMessage::parse_text("ALL_IN").unwrap().unwrap(),
+            Protocol::Json.parse(r#"{"type":"all_in"}"#).unwrap().unwrap()
+        );
+        assert_eq!(ClientMessage::parse_text("MUCK").unwrap().unwrap(), Protocol::Json.parse(r#"{"type":"muck"}"#).unwrap().unwrap());
+        assert_eq!(ClientMessage::parse_text("SHOW_HAND").unwrap().unwrap(), ClientMessage::ShowHand);
+        assert!(Protocol::Json.parse(r#"{"type":"dance"}"#).is_err());
+    }
+
+    #[test]
+    fn test_chat_commands_keep_the_text_as_typed() {
+        assert_eq!(
+            ClientMessage::parse_text("SAY  nice  hand, bob ").unwrap().unwrap(),
+            ClientMessage::Say { text: "nice  hand, bob".to_string() }
+        );
+        assert_eq!(
+            ClientMessage::parse_text("WHISPER bob  fold pre").unwrap().unwrap(),
+            ClientMessage::Whisper { to: "bob".to_string(), text: "fold pre".to_string() }
+        );
+        assert!(ClientMessage::parse_text("SAY").is_err());
+        assert!(ClientMessage::parse_text("WHISPER bob").is_err());
+    }
+
+    #[test]
+    fn test_server_messages_render_as_text_or_json_lines() {
+        let event = ServerMessage::Event(TableEvent::ShowsCards {
+            player: "alice".to_string(),
+            cards: vec![Card { rank: Rank::Ace, suit: Suit::Hearts }],
+        });
+        assert_eq!(Protocol::Text.render(&event), "alice shows Ah\n");
+        assert_eq!(
+            Protocol::Json.render(&event),
+            "{\"type\":\"event\",\"event\":\"shows_cards\",\"player\":\"alice\",\"cards\":[\"Ah\"]}\n"
+        );
+        let hand = ServerMessage::Hand { cards: vec![Card { rank: Rank::Ace, suit: Suit::Hearts }], pot: 3.0, community_cards: vec![] };
+        assert_eq!(Protocol::Text.render(&hand), "Your cards: Ah\nPot: 3\nCommunity cards: \n");
+        assert_eq!(Protocol::Json.render(&ServerMessage::Bye), "{\"type\":\"bye\"}\n");
+        assert_eq!(Protocol::from_handshake("PROTOCOL json"), Some(Protocol::Json));
+        assert_eq!(Protocol::from_handshake("REGISTER json"), None);
+    }
+
+    #[test]
+    fn test_showdown_and_actions_carry_typed_fields() {
+        let acted = ServerMessage::Event(TableEvent::Acted { player: "bob".to_string(), action: ActionTaken::Bet { amount: 10.0, to: 12.0 } });
+        assert_eq!(Protocol::Text.render(&acted), "bob bets 10\n");
+        assert_eq!(
+            Protocol::Json.render(&acted),
+            "{\"type\":\"event\",\"event\":\"acted\",\"player\":\"bob\",\"action\":{\"kind\":\"bet\",\"amount\":10.0,\"to\":12.0}}\n"
+        );
+        let showdown = ServerMessage::Event(TableEvent::Showdown(Showdown {
+            reveals: vec![
+                RevealedHand { player: "alice".to_string(), cards: Some(vec![Card { rank: Rank::Ace, suit: Suit::Hearts }]) },
+                RevealedHand { player: "bob".to_string(), cards: None },
+            ],
+            boards: vec![vec![Card { rank: Rank::Two, suit: Suit::Clubs }]],
+            double_board: false,
+            pots: vec![PotWon {
+                amount: 20.0,
+                run: 0,
+                high_winners: vec!["alice".to_string()],
+                low_winners: vec![],
+                payouts: vec![Payout { player: "alice".to_string(), amount: 20.0, hand: Some("Pair of Aces".to_string()) }],
+            }],
+        }));
+        assert_eq!(
+            Protocol::Text.render(&showdown),
+            "Showdown: alice shows Ah, bob mucks, Run 1 board: 2c, alice wins 20 with Pair of Aces\n"
+        );
+        let json: serde_json::Value = serde_json::from_str(&Protocol::Json.render(&showdown)).unwrap();
+        assert_eq!(json["event"], "showdown");
+        assert!(json["reveals"][1]["cards"].is_null());
+        assert_eq!(json["pots"][0]["payouts"][0]["hand"], "Pair of Aces");
+    }
+}
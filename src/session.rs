@@ -0,0 +1,79 @@
+use crate::models::UserId;
+use rand::Rng;
+use std::collections::HashMap;
+
+/// Length of a session token, in hex digits
+const TOKEN_LEN: usize = 32;
+
+/// A fresh random session token, e.g. `9f2c...`, used with RESUME to reattach a new
+/// connection to an account
+fn new_token() -> String {
+    let mut rng = rand::thread_rng();
+    (0..TOKEN_LEN).map(|_| format!("{:x}", rng.gen_range(0..16u8))).collect()
+}
+
+/// Gives `user` a fresh token in `sessions`, expiring any they were issued before, so
+/// only the token from their latest register, login or password change resumes them
+pub fn issue_token(sessions: &mut HashMap<String, UserId>, user: &str) -> String {
+    sessions.retain(|_, owner| owner != user);
+    let token = new_token();
+    sessions.insert(token.clone(), user.to_string());
+    token
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+
+    #[test]
+    fn test_tokens_are_random_hex() {
+        let token = new_token();
+        assert_eq!(token.len(), TOKEN_LEN);
+        assert!(token.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(token, new_token());
+    }
+
+    #[test]
+    fn test_a_new_token_expires_the_users_old_ones() {
+        let mut sessions = HashMap::new();
+        let first = issue_token(&mut sessions, "alice");
+        let bob = issue_token(&mut sessions, "bob");
+        let second = issue_token(&mut sessions, "alice");
+        assert!(!sessions.contains_key(&first));
+        assert_eq!(sessions.get(&second).map(String::as_str), Some("alice"));
+        assert_eq!(sessions.get(&bob).map(String::as_str), Some("bob"));
+        assert_eq!(sessions.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_resume_moves_the_session_to_a_new_connection() {
+        let url = start_gateway().await;
+        let (mut old, _) = connect_async(&url).await.unwrap();
+        old.send(Message::text("PROTOCOL json")).await.unwrap();
+        old.send(Message::text(r#"{"type":"register","name":"alice","password":"hunter22"}"#)).await.unwrap();
+        let registered: serde_json::Value = serde_json::from_str(&wait_for(&mut old, "registered").await).unwrap();
+        let token = registered["token"].as_str().unwrap().to_string();
+        old.send(Message::text(r#"{"type":"create_table","table":"t"}"#)).await.unwrap();
+        old.send(Message::text(r#"{"type":"join_table","table":"t"}"#)).await.unwrap();
+        wait_for(&mut old, "joined").await;
+        let mut bob = json_client(&url, "bob").await;
+        bob.send(Message::text(r#"{"type":"join_table","table":"t"}"#)).await.unwrap();
+        wait_for(&mut bob, "joined").await;
+
+        let mut new = json_client(&url, "carol").await;
+        new.send(Message::text(r#"{"type":"resume","token":"nope"}"#)).await.unwrap();
+        wait_for(&mut new, "Unknown or expired session token").await;
+        new.send(Message::text(format!(r#"{{"type":"resume","token":"{}"}}"#, token))).await.unwrap();
+        let resumed = wait_for(&mut new, "resumed").await;
+        assert!(resumed.contains(r#""name":"alice""#) && resumed.contains(r#""table":"t""#));
+        // The resync is alice's own view, with her hole cards
+        let state: serde_json::Value = serde_json::from_str(&wait_for(&mut new, r#""type":"state""#).await).unwrap();
+        assert_eq!(state["viewer"], "alice");
+        let seats = state["seats"].as_array().unwrap();
+        assert!(seats.iter().any(|seat| seat["name"] == "alice" && seat["hole_cards"].as_array().is_some_and(|c| c.len() == 2)));
+
+        old.send(Message::text(r#"{"type":"show_state"}"#)).await.unwrap();
+        wait_for(&mut old, "resumed on another connection").await;
+    }
+}
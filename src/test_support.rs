@@ -0,0 +1,58 @@
+use crate::config::Config;
+use crate::models::ServerState;
+use crate::websocket::serve_websocket;
+use futures_util::StreamExt;
+use std::sync::{Arc, Mutex};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+pub use futures_util::SinkExt;
+pub use tokio_tungstenite::connect_async;
+pub use tokio_tungstenite::tungstenite::Message;
+
+/// A test client connected to the WebSocket gateway
+pub type Client = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Starts a gateway on a free local port and returns its address
+pub async fn start_gateway() -> String {
+    start_gateway_with(Config::default()).await
+}
+
+/// Starts a gateway with the given settings on a free local port and returns its address
+pub async fn start_gateway_with(config: Config) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let state = ServerState { config, ..ServerState::default() };
+    tokio::spawn(serve_websocket(listener, Arc::new(Mutex::new(state))));
+    format!("ws://{}", addr)
+}
+
+/// Reads the next frame, which must be text
+pub async fn next_frame(stream: &mut Client) -> String {
+    match stream.next().await {
+        Some(Ok(Message::Text(text))) => text.to_string(),
+        other => panic!("expected a text frame, got {:?}", other),
+    }
+}
+
+/// Reads frames until one contains `needle`, returning it
+pub async fn wait_for(stream: &mut Client, needle: &str) -> String {
+    let wait = async {
+        loop {
+            let frame = next_frame(stream).await;
+            if frame.contains(needle) {
+                return frame;
+            }
+        }
+    };
+    tokio::time::timeout(std::time::Duration::from_secs(5), wait).await.expect(needle)
+}
+
+/// Connects and registers a client that speaks JSON
+pub async fn json_client(url: &str, name: &str) -> Client {
+    let (mut stream, _) = connect_async(url).await.unwrap();
+    stream.send(Message::text("PROTOCOL json")).await.unwrap();
+    stream.send(Message::text(format!(r#"{{"type":"register","name":"{}","password":"hunter22"}}"#, name))).await.unwrap();
+    wait_for(&mut stream, "registered").await;
+    stream
+}
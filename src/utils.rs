@@ -1,81 +1,71 @@
 use crate::models::*;
+use crate::clock::ensure_clock;
+use crate::protocol::{GameState, ServerMessage};
+use crate::view::PlayerView;
 use tokio::sync::mpsc::UnboundedSender;
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex};
 
-/// Sends the current game state to the user, including their cards, pot, community cards, and turn info.
-pub async fn send_game_state(state: &Arc<Mutex<ServerState>>, user_id: &Option<UserId>, writer: &UnboundedSender<String>, _next_player: Option<String>, round_ended: bool, winner: Option<String>) {
-    let (cards, pot, comm_cards, current_player, folded, _winner_str) = {
+/// Sends a message to each of the given users who is connected
+pub fn send_to_players(writers: &HashMap<UserId, UnboundedSender<ServerMessage>>, players: &HashSet<UserId>, message: &ServerMessage) {
+    for player in players {
+        if let Some(writer) = writers.get(player) {
+            let _ = writer.send(message.clone());
+        }
+    }
+}
+
+/// Sends the current game state to the user, as they are allowed to see it: their cards,
+/// the pot, community cards and turn info.
+pub async fn send_game_state(state: &Arc<Mutex<ServerState>>, user_id: &Option<UserId>, writer: &UnboundedSender<ServerMessage>, round_ended: bool) {
+    let (message, table_id) = {
         let state = state.lock().unwrap();
-        let mut cards = None;
-        let mut pot = 0.0;
-        let mut comm_cards = vec![];
-        let mut current_player = None;
-        let mut folded = false;
-        let _winner_str: Option<String> = None;
-        for table in state.tables.values() {
-            if let Some(game) = &table.game {
-                for player in &game.players {
-                    if let Some(uid) = user_id {
-                        if &player.name == uid {
-                            cards = Some(player.hole_cards.clone());
-                            folded = player.state == crate::player::PlayerState::Folded;
-                        }
-                    }
-                }
-                pot = game.get_pot();
-                comm_cards = game.get_community_cards().to_vec();
-                current_player = game.get_current_player().map(|p| p.name.clone());
+        let user = user_id.as_ref().and_then(|uid| state.users.get(uid));
+        let table_id = user.and_then(|u| u.table.clone());
+        let watching = user.and_then(|u| u.watching.clone());
+        // Only the user's own table, so other tables' games never leak in
+        let game = table_id.as_ref()
+            .or(watching.as_ref())
+            .and_then(|t| state.tables.get(t))
+            .and_then(|t| t.game.as_ref());
+        let message = user_id.as_ref().zip(game).and_then(|(uid, game)| {
+            // Spectators have no seat, so their view shows no hidden cards at all
+            let player = game.players.iter().find(|p| &p.name == uid);
+            if player.is_none() && watching.is_none() {
+                return None;
             }
-        }
-        (cards, pot, comm_cards, current_player, folded, winner.clone())
+            let view = PlayerView::new(game, uid);
+            let your_turn = player.is_some() && view.current_player.as_ref() == Some(uid);
+            Some(ServerMessage::State(GameState {
+                view,
+                your_turn,
+                time_left: player.filter(|_| your_turn).map(|p| (game.clock.action_secs, p.time_bank)),
+                round_ended,
+            }))
+        });
+        (message, table_id)
     };
-    if let Some(cards) = cards {
-        let _ = writer.send(format!("Your cards: {:?}\nPot: {}\nCommunity cards: {:?}\n", cards, pot, comm_cards));
-        let commands = "Available commands: BET <amount>, CALL, CHECK, FOLD, SHOW, SHOW_STATE, QUIT";
-        let _ = writer.send(format!("{}\n", commands));
-        if let Some(cp) = current_player {
-            let is_your_turn = if let Some(uid) = user_id { &cp == uid } else { false };
-            let _ = writer.send(format!("Current player: {}{}\n", cp, if is_your_turn { " (you)" } else { "" }));
-            if is_your_turn {
-                let _ = writer.send("Your turn! You have 15 seconds...\n".to_string());
-                // Start a timer for 15 seconds for auto-fold
-                let writer = writer.clone();
-                let uid = cp.clone();
-                let state = Arc::clone(state);
-                tokio::spawn(async move {
-                    tokio::time::sleep(std::time::Duration::from_secs(15)).await;
-                    let mut should_fold = false;
-                    {
-                        let mut state = state.lock().unwrap();
-                        for table in state.tables.values_mut() {
-                            if let Some(game) = &mut table.game {
-                                if let Some(idx) = game.players.iter().position(|p| p.name == uid) {
-                                    if let Some(current) = game.get_current_player() {
-                                        if current.name == uid && game.players[idx].state != crate::player::PlayerState::Folded {
-                                            should_fold = true;
-                                            let _ = game.player_action(idx, crate::player::PlayerAction::Fold);
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    if should_fold {
-                        let _ = writer.send("You did not act in time. Auto-folded.\n".to_string());
-                    }
-                });
-            }
-        }
-        if folded {
-            let _ = writer.send("You have folded\n".to_string());
-        }
-        if let Some(winner) = _winner_str {
-            let _ = writer.send(format!("Winner: {}\n", winner));
-        }
-        if round_ended {
-            let _ = writer.send("Betting round ended, next round started\n".to_string());
-        }
-    } else {
-        let _ = writer.send("You are not in a game\n".to_string());
+    if let Some(table_id) = &table_id {
+        ensure_clock(state, table_id);
+    }
+    let _ = writer.send(message.unwrap_or_else(|| ServerMessage::error("You are not in a game")));
+}
+
+/// Sends every player seated at the table their own view of the game, which tells
+/// whoever is next to act that it is their turn, and spectators the public view.
+pub async fn broadcast_game_state(state: &Arc<Mutex<ServerState>>, table_id: &TableId, round_ended: bool) {
+    let seats: Vec<(UserId, UnboundedSender<ServerMessage>)> = {
+        let state = state.lock().unwrap();
+        let Some(table) = state.tables.get(table_id) else {
+            return;
+        };
+        table.audience().iter()
+            .filter_map(|p| state.writers.get(p).map(|w| (p.clone(), w.clone())))
+            .collect()
+    };
+    for (uid, writer) in seats {
+        send_game_state(state, &Some(uid), &writer, round_ended).await;
     }
-} 
\ No newline at end of file
+    // The clock keeps running even when nobody at the table is connected
+    ensure_clock(state, table_id);
+}
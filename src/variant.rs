@@ -0,0 +1,472 @@
+use std::fmt;
+use std::sync::Arc;
+
+use crate::card::{Card, Rank};
+use crate::deck::Deck;
+use crate::game::BettingRound;
+use crate::hand::{evaluate_hand_wild, evaluate_hand_with, evaluate_omaha_high, evaluate_omaha_low, evaluate_razz, evaluate_deuce_seven, EvaluatedHand, HandRanking, LowHand, Wilds};
+
+/// Most players at a stud table. Eight players take 48 cards by sixth street;
+/// a ninth would leave someone without one.
+pub const MAX_STUD_PLAYERS: usize = 8;
+
+/// How much a player may bet or raise
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BettingStructure {
+    /// Any amount up to the player's stack
+    NoLimit,
+    /// Bets and raises are one fixed size per street, capped at four bets per round
+    FixedLimit,
+}
+
+/// Which hands a pot is awarded to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PotSplit {
+    /// The best high hand takes the pot
+    High,
+    /// The pot is halved between the best high and the best qualifying low;
+    /// the high hand scoops when no low qualifies
+    HighLow,
+    /// The best low hand takes the pot
+    Low,
+}
+
+/// The rules of a poker variant: deck, deal schedule, street structure, hand
+/// evaluation and pot splitting. `Game` runs any implementation; the defaults
+/// describe no-limit Texas Hold'em, so a variant only overrides what it changes.
+pub trait GameVariant: fmt::Debug + Send + Sync {
+    /// Human readable name of the variant
+    fn name(&self) -> &str;
+
+    /// The unshuffled cards the variant is played with
+    fn deck(&self) -> Vec<Card> {
+        Deck::standard_cards(Rank::Two)
+    }
+
+    /// Number of hole cards dealt to each player; in stud these are the two down
+    /// cards dealt on third street
+    fn hole_card_count(&self) -> usize {
+        2
+    }
+
+    /// Community cards dealt on each street after the first betting round
+    /// (flop, turn, river); empty when the variant has no board
+    fn board_deals(&self) -> &'static [usize] {
+        &[3, 1, 1]
+    }
+
+    /// Whether cards are dealt per player face up and face down instead of on a shared board
+    fn is_stud(&self) -> bool {
+        false
+    }
+
+    /// Most players a table can seat. Stud deals up to seven cards each, so past
+    /// eight players the deck cannot cover sixth street.
+    fn max_players(&self) -> Option<usize> {
+        self.is_stud().then_some(MAX_STUD_PLAYERS)
+    }
+
+    /// Number of draw rounds in a hand
+    fn draw_rounds(&self) -> usize {
+        0
+    }
+
+    /// Whether players replace hole cards in draw rounds instead of sharing a board
+    fn is_draw(&self) -> bool {
+        self.draw_rounds() > 0
+    }
+
+    /// The betting round after which each player discards one hole card, if any
+    fn discard_after(&self) -> Option<BettingRound> {
+        None
+    }
+
+    /// Betting structure used at tables of this variant
+    fn betting_structure(&self) -> BettingStructure {
+        BettingStructure::NoLimit
+    }
+
+    /// Which hands win the pot at showdown
+    fn pot_split(&self) -> PotSplit {
+        PotSplit::High
+    }
+
+    /// Whether the best high hand wins (all or half of) the pot; lowball variants play for low only
+    fn plays_high(&self) -> bool {
+        self.pot_split() != PotSplit::Low
+    }
+
+    /// Ranking rules used to compare high hands
+    fn ranking(&self) -> HandRanking {
+        HandRanking::Standard
+    }
+
+    /// Evaluates the best high hand a player can make with their hole cards and the board
+    fn evaluate_high(&self, hole_cards: &[Card], board: &[Card]) -> EvaluatedHand {
+        let mut all_cards = hole_cards.to_vec();
+        all_cards.extend_from_slice(board);
+        evaluate_hand_with(&all_cards, self.ranking())
+    }
+
+    /// Evaluates the best qualifying low hand, if the variant plays for low at all
+    fn evaluate_low(&self, _hole_cards: &[Card], _board: &[Card]) -> Option<LowHand> {
+        None
+    }
+}
+
+/// Looks up a variant by the name a client typed (e.g. `holdem`, `omaha8`).
+/// High-only variants take wild card modifiers: `draw+joker`, `holdem+jokers`,
+/// `stud+deuces` or `draw+jokers+deuces`.
+pub fn parse_variant(name: &str) -> Option<Arc<dyn GameVariant>> {
+    let name = name.to_ascii_lowercase();
+    let mut parts = name.split('+');
+    let base = parse_base_variant(parts.next()?)?;
+    let mut jokers = 0;
+    let mut wild_rank = None;
+    for modifier in parts {
+        match modifier {
+            "joker" => jokers = 1,
+            "jokers" => jokers = 2,
+            "deuces" => wild_rank = Some(Rank::Two),
+            _ => return None,
+        }
+    }
+    if jokers == 0 && wild_rank.is_none() {
+        return Some(base);
+    }
+    let wild: Arc<dyn GameVariant> = Arc::new(WildCards::new(base, jokers, wild_rank)?);
+    Some(wild)
+}
+
+fn parse_base_variant(name: &str) -> Option<Arc<dyn GameVariant>> {
+    let variant: Arc<dyn GameVariant> = match name {
+        "holdem" | "texas" | "nlhe" => Arc::new(TexasHoldem),
+        "omaha8" | "omaha-hilo" | "plo8" | "o8" => Arc::new(OmahaHiLo),
+        "shortdeck" | "6plus" | "6+" => Arc::new(ShortDeck { trips_beat_straight: true }),
+        "shortdeck-classic" | "6plus-classic" => Arc::new(ShortDeck { trips_beat_straight: false }),
+        "stud" | "7stud" | "seven-card-stud" => Arc::new(SevenCardStud),
+        "razz" => Arc::new(Razz),
+        "draw" | "5draw" | "five-card-draw" => Arc::new(FiveCardDraw),
+        "27td" | "2-7" | "triple-draw" => Arc::new(TripleDraw),
+        "pineapple" => Arc::new(Pineapple),
+        "crazy-pineapple" | "crazypineapple" => Arc::new(CrazyPineapple),
+        _ => return None,
+    };
+    Some(variant)
+}
+
+/// The variant played when a table is created without naming one
+pub fn default_variant() -> Arc<dyn GameVariant> {
+    Arc::new(TexasHoldem)
+}
+
+/// No-limit Texas Hold'em, high hand only
+#[derive(Debug, Clone, Copy)]
+pub struct TexasHoldem;
+
+impl GameVariant for TexasHoldem {
+    fn name(&self) -> &str {
+        "Texas Hold'em"
+    }
+}
+
+/// Omaha eight-or-better: the pot splits between the best high and the best qualifying low
+#[derive(Debug, Clone, Copy)]
+pub struct OmahaHiLo;
+
+impl GameVariant for OmahaHiLo {
+    fn name(&self) -> &str {
+        "Omaha Hi-Lo"
+    }
+
+    fn hole_card_count(&self) -> usize {
+        4
+    }
+
+    fn pot_split(&self) -> PotSplit {
+        PotSplit::HighLow
+    }
+
+    fn evaluate_high(&self, hole_cards: &[Card], board: &[Card]) -> EvaluatedHand {
+        evaluate_omaha_high(hole_cards, board)
+    }
+
+    fn evaluate_low(&self, hole_cards: &[Card], board: &[Card]) -> Option<LowHand> {
+        evaluate_omaha_low(hole_cards, board)
+    }
+}
+
+/// Short-deck (6+) Hold'em with a 36-card deck; a flush beats a full house
+#[derive(Debug, Clone, Copy)]
+pub struct ShortDeck {
+    /// Whether three of a kind beats a straight
+    pub trips_beat_straight: bool,
+}
+
+impl GameVariant for ShortDeck {
+    fn name(&self) -> &str {
+        if self.trips_beat_straight {
+            "Short-deck Hold'em"
+        } else {
+            "Short-deck Hold'em (straights beat trips)"
+        }
+    }
+
+    fn deck(&self) -> Vec<Card> {
+        // The deuces through fives are removed
+        Deck::standard_cards(Rank::Six)
+    }
+
+    fn ranking(&self) -> HandRanking {
+        HandRanking::ShortDeck { trips_beat_straight: self.trips_beat_straight }
+    }
+}
+
+/// Seven-Card Stud: no community cards, each player gets three down and four up cards
+#[derive(Debug, Clone, Copy)]
+pub struct SevenCardStud;
+
+impl GameVariant for SevenCardStud {
+    fn name(&self) -> &str {
+        "Seven-Card Stud"
+    }
+
+    fn board_deals(&self) -> &'static [usize] {
+        &[]
+    }
+
+    fn is_stud(&self) -> bool {
+        true
+    }
+}
+
+/// Razz: seven-card stud played for the ace-to-five low only
+#[derive(Debug, Clone, Copy)]
+pub struct Razz;
+
+impl GameVariant for Razz {
+    fn name(&self) -> &str {
+        "Razz"
+    }
+
+    fn board_deals(&self) -> &'static [usize] {
+        &[]
+    }
+
+    fn is_stud(&self) -> bool {
+        true
+    }
+
+    fn pot_split(&self) -> PotSplit {
+        PotSplit::Low
+    }
+
+    fn evaluate_low(&self, hole_cards: &[Card], board: &[Card]) -> Option<LowHand> {
+        let mut all_cards = hole_cards.to_vec();
+        all_cards.extend_from_slice(board);
+        Some(evaluate_razz(&all_cards))
+    }
+}
+
+/// Five-Card Draw: five hole cards, one draw, betting before and after it
+#[derive(Debug, Clone, Copy)]
+pub struct FiveCardDraw;
+
+impl GameVariant for FiveCardDraw {
+    fn name(&self) -> &str {
+        "Five-Card Draw"
+    }
+
+    fn hole_card_count(&self) -> usize {
+        5
+    }
+
+    fn board_deals(&self) -> &'static [usize] {
+        &[]
+    }
+
+    fn draw_rounds(&self) -> usize {
+        1
+    }
+}
+
+/// 2-7 Triple Draw: deuce-to-seven lowball with three draws and fixed-limit betting
+#[derive(Debug, Clone, Copy)]
+pub struct TripleDraw;
+
+impl GameVariant for TripleDraw {
+    fn name(&self) -> &str {
+        "2-7 Triple Draw"
+    }
+
+    fn hole_card_count(&self) -> usize {
+        5
+    }
+
+    fn board_deals(&self) -> &'static [usize] {
+        &[]
+    }
+
+    fn draw_rounds(&self) -> usize {
+        3
+    }
+
+    fn betting_structure(&self) -> BettingStructure {
+        BettingStructure::FixedLimit
+    }
+
+    fn pot_split(&self) -> PotSplit {
+        PotSplit::Low
+    }
+
+    fn evaluate_low(&self, hole_cards: &[Card], _board: &[Card]) -> Option<LowHand> {
+        Some(evaluate_deuce_seven(hole_cards))
+    }
+}
+
+/// Hold'em with three hole cards; one is discarded before the flop
+#[derive(Debug, Clone, Copy)]
+pub struct Pineapple;
+
+impl GameVariant for Pineapple {
+    fn name(&self) -> &str {
+        "Pineapple"
+    }
+
+    fn hole_card_count(&self) -> usize {
+        3
+    }
+
+    fn discard_after(&self) -> Option<BettingRound> {
+        Some(BettingRound::PreFlop)
+    }
+}
+
+/// Hold'em with three hole cards; one is discarded after the flop
+#[derive(Debug, Clone, Copy)]
+pub struct CrazyPineapple;
+
+impl GameVariant for CrazyPineapple {
+    fn name(&self) -> &str {
+        "Crazy Pineapple"
+    }
+
+    fn hole_card_count(&self) -> usize {
+        3
+    }
+
+    fn discard_after(&self) -> Option<BettingRound> {
+        Some(BettingRound::Flop)
+    }
+}
+
+/// A high-only variant played with wild cards: jokers shuffled into the deck,
+/// a declared wild rank, or both
+#[derive(Debug)]
+pub struct WildCards {
+    base: Arc<dyn GameVariant>,
+    jokers: usize,
+    wilds: Wilds,
+    name: String,
+}
+
+impl WildCards {
+    /// Adds `jokers` jokers (at most two) and an optional wild rank to `base`.
+    /// Returns `None` for variants that play for low, which wild cards do not support.
+    pub fn new(base: Arc<dyn GameVariant>, jokers: usize, wild_rank: Option<Rank>) -> Option<Self> {
+        if base.pot_split() != PotSplit::High {
+            return None;
+        }
+        let wild = match (jokers, wild_rank) {
+            (0, _) => "deuces wild",
+            (_, None) => "jokers wild",
+            (_, Some(_)) => "jokers and deuces wild",
+        };
+        let name = format!("{} ({})", base.name(), wild);
+        let wilds = Wilds { jokers: jokers > 0, rank: wild_rank };
+        Some(WildCards { base, jokers: jokers.min(2), wilds, name })
+    }
+}
+
+impl GameVariant for WildCards {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn deck(&self) -> Vec<Card> {
+        let mut cards = self.base.deck();
+        cards.extend(Deck::jokers(self.jokers));
+        cards
+    }
+
+    fn hole_card_count(&self) -> usize {
+        self.base.hole_card_count()
+    }
+
+    fn board_deals(&self) -> &'static [usize] {
+        self.base.board_deals()
+    }
+
+    fn is_stud(&self) -> bool {
+        self.base.is_stud()
+    }
+
+    fn draw_rounds(&self) -> usize {
+        self.base.draw_rounds()
+    }
+
+    fn discard_after(&self) -> Option<BettingRound> {
+        self.base.discard_after()
+    }
+
+    fn betting_structure(&self) -> BettingStructure {
+        self.base.betting_structure()
+    }
+
+    fn ranking(&self) -> HandRanking {
+        self.base.ranking()
+    }
+
+    fn evaluate_high(&self, hole_cards: &[Card], board: &[Card]) -> EvaluatedHand {
+        let mut all_cards = hole_cards.to_vec();
+        all_cards.extend_from_slice(board);
+        evaluate_hand_wild(&all_cards, self.ranking(), self.wilds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_variant_names() {
+        assert_eq!(parse_variant("holdem").unwrap().name(), "Texas Hold'em");
+        assert_eq!(parse_variant("O8").unwrap().name(), "Omaha Hi-Lo");
+        assert_eq!(parse_variant("6plus-classic").unwrap().name(), "Short-deck Hold'em (straights beat trips)");
+        assert!(parse_variant("badugi").is_none());
+    }
+
+    #[test]
+    fn test_wild_card_modifiers() {
+        let draw = parse_variant("draw+joker").unwrap();
+        assert_eq!(draw.name(), "Five-Card Draw (jokers wild)");
+        assert_eq!(draw.deck().len(), 53);
+        assert_eq!(draw.draw_rounds(), 1);
+        assert_eq!(parse_variant("holdem+jokers+deuces").unwrap().deck().len(), 54);
+        assert_eq!(parse_variant("stud+deuces").unwrap().name(), "Seven-Card Stud (deuces wild)");
+        assert_eq!(parse_variant("stud+deuces").unwrap().max_players(), Some(MAX_STUD_PLAYERS));
+        assert!(parse_variant("razz+jokers").is_none());
+        assert!(parse_variant("holdem+sevens").is_none());
+    }
+
+    #[test]
+    fn test_defaults_describe_holdem() {
+        let holdem = default_variant();
+        assert_eq!(holdem.deck().len(), 52);
+        assert_eq!(holdem.hole_card_count(), 2);
+        assert_eq!(holdem.board_deals(), &[3, 1, 1]);
+        assert_eq!(holdem.pot_split(), PotSplit::High);
+        assert!(!holdem.is_stud() && !holdem.is_draw());
+        assert_eq!(holdem.max_players(), None);
+    }
+}
@@ -0,0 +1,172 @@
+use crate::card::Card;
+use crate::game::Game;
+use crate::history::HandEvent;
+use crate::player::PlayerState;
+use serde::Serialize;
+
+/// The game as one player is allowed to see it: their own hole cards, everyone's
+/// face-up cards and the hands shown at showdown, but never another player's
+/// face-down cards otherwise.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlayerView {
+    /// The player the view was made for
+    pub viewer: String,
+    pub variant: String,
+    pub hand_in_progress: bool,
+    pub pot: f64,
+    pub current_bet: f64,
+    pub community_cards: Vec<Card>,
+    /// Every board when a double-board bomb pot is played, empty otherwise
+    pub boards: Vec<Vec<Card>>,
+    /// Whose turn it is, if anyone's
+    pub current_player: Option<String>,
+    pub seats: Vec<SeatView>,
+    /// Hands shown in the last hand, at showdown or with SHOW_CARDS afterwards
+    pub last_shown: Vec<ShownHand>,
+}
+
+/// One player at the table, as the viewer sees them
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SeatView {
+    pub name: String,
+    pub balance: f64,
+    pub chips_in_play: f64,
+    pub state: PlayerState,
+    /// Face-down cards, for the viewer's own seat and hands shown at showdown
+    pub hole_cards: Option<Vec<Card>>,
+    /// How many face-down cards the player holds
+    pub hidden_cards: usize,
+    pub up_cards: Vec<Card>,
+    /// Whether the player has lost their connection
+    pub disconnected: bool,
+}
+
+/// A hand turned face up
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ShownHand {
+    pub player: String,
+    pub cards: Vec<Card>,
+}
+
+impl PlayerView {
+    pub fn new(game: &Game, viewer: &str) -> Self {
+        // Hands turned over at the showdown under way are seen by everyone
+        let shown = |i: usize| game.hand_in_progress && game.reveals.iter().any(|r| r.player == i && r.cards.is_some());
+        let seats = game.players.iter().enumerate()
+            .map(|(i, player)| SeatView {
+                name: player.name.clone(),
+                balance: player.balance,
+                chips_in_play: player.chips_in_play,
+                state: player.state.clone(),
+                hole_cards: (player.name == viewer || shown(i)).then(|| player.hole_cards.clone()),
+                hidden_cards: player.hole_cards.len(),
+                up_cards: player.up_cards.clone(),
+                disconnected: player.disconnected_at.is_some(),
+            })
+            .collect();
+        let last_shown = game.last_hand.iter()
+            .flat_map(|hand| &hand.events)
+            .filter_map(|event| match event {
+                HandEvent::Shows { player, cards } => Some(ShownHand { player: player.clone(), cards: cards.clone() }),
+                _ => None,
+            })
+            .collect();
+        PlayerView {
+            viewer: viewer.to_string(),
+            variant: game.variant.name().to_string(),
+            hand_in_progress: game.hand_in_progress,
+            pot: game.get_pot(),
+            current_bet: game.current_bet,
+            community_cards: game.get_community_cards().to_vec(),
+            boards: if game.is_double_board() { game.boards.clone() } else { Vec::new() },
+            // Nobody is to act between hands or while waiting for players
+            current_player: game.turn_key().and(game.get_current_player()).map(|p| p.name.clone()),
+            seats,
+            last_shown,
+        }
+    }
+
+    /// The viewer's own seat, if they are playing
+    pub fn own_seat(&self) -> Option<&SeatView> {
+        self.seats.iter().find(|s| s.name == self.viewer)
+    }
+
+    /// The viewer's face-down and face-up cards together
+    pub fn own_cards(&self) -> Vec<Card> {
+        self.own_seat()
+            .map(|seat| {
+                let mut cards = seat.hole_cards.clone().unwrap_or_default();
+                cards.extend_from_slice(&seat.up_cards);
+                cards
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::player::PlayerAction;
+    use crate::variant::{SevenCardStud, TexasHoldem};
+    use std::sync::Arc;
+
+    fn game(variant: Arc<dyn crate::variant::GameVariant>, players: usize) -> Game {
+        let mut game = Game::new(variant, 1.0, 2.0);
+        for i in 0..players {
+            game.add_player(format!("p{}", i), 100.0);
+        }
+        game.start_new_hand().unwrap();
+        game
+    }
+
+    /// Fails if any of the player's face-down cards appears in the serialized view
+    fn assert_hidden(json: &str, game: &Game, player: usize) {
+        for card in &game.players[player].hole_cards {
+            assert!(!json.contains(&format!("\"{}\"", card)), "{} leaked to another player", card);
+        }
+    }
+
+    #[test]
+    fn test_view_hides_opponents_hole_cards() {
+        let game = game(Arc::new(TexasHoldem), 3);
+        let view = PlayerView::new(&game, "p0");
+        let json = serde_json::to_string(&view).unwrap();
+        assert_eq!(view.own_cards(), game.players[0].hole_cards);
+        assert_hidden(&json, &game, 1);
+        assert_hidden(&json, &game, 2);
+        assert!(view.seats[1..].iter().all(|s| s.hole_cards.is_none() && s.hidden_cards == 2));
+    }
+
+    #[test]
+    fn test_view_shows_stud_up_cards_but_not_down_cards() {
+        let game = game(Arc::new(SevenCardStud), 2);
+        let view = PlayerView::new(&game, "p1");
+        let json = serde_json::to_string(&view).unwrap();
+        assert_hidden(&json, &game, 0);
+        assert_eq!(view.seats[0].up_cards, game.players[0].up_cards);
+        assert_eq!(view.seats[0].up_cards.len(), 1);
+    }
+
+    #[test]
+    fn test_view_shows_only_hands_turned_over_at_showdown() {
+        let mut game = game(Arc::new(TexasHoldem), 2);
+        for i in 0..2 {
+            game.player_action(i, PlayerAction::AllIn).unwrap();
+        }
+        game.deal_flop();
+        game.deal_turn();
+        game.deal_river();
+        game.showdown();
+        // Seen from the rail, so neither player's own cards are in the view
+        let view = PlayerView::new(&game, "rail");
+        let json = serde_json::to_string(&view).unwrap();
+        assert!(!view.last_shown.is_empty());
+        for (name, cards) in &game.last_hole_cards {
+            match view.last_shown.iter().find(|s| &s.player == name) {
+                Some(shown) => assert_eq!(&shown.cards, cards),
+                // A mucked hand stays hidden
+                None => assert!(cards.iter().all(|c| !json.contains(&format!("\"{}\"", c)))),
+            }
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use crate::models::*;
+use crate::commands::handle_disconnect;
+use crate::network::{acknowledge_protocol, handle_line, ConnectionSlot, BANNER, SERVER_FULL};
+use crate::protocol::{Protocol, ServerMessage};
+use futures_util::stream::SplitStream;
+use futures_util::{SinkExt, StreamExt};
+use std::sync::{Arc, Mutex};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio_tungstenite::WebSocketStream;
+use tokio_tungstenite::tungstenite::Message;
+
+/// Accepts WebSocket connections on the listener, sharing the server state with the TCP server.
+pub async fn serve_websocket(listener: TcpListener, state: Arc<Mutex<ServerState>>) {
+    loop {
+        let Ok((socket, _)) = listener.accept().await else {
+            continue;
+        };
+        let state = Arc::clone(&state);
+        tokio::spawn(async move {
+            handle_websocket(socket, state).await;
+        });
+    }
+}
+
+/// Handles a single WebSocket client. Every frame carries one message, in the same text
+/// or JSON form as a line over TCP, and the first frame may pick the protocol the same way.
+pub async fn handle_websocket(socket: TcpStream, state: Arc<Mutex<ServerState>>) {
+    let Ok(stream) = tokio_tungstenite::accept_async(socket).await else {
+        return;
+    };
+    let (mut writer, mut reader) = stream.split();
+    let mut user_id: Option<UserId> = None;
+    let Some(_slot) = ConnectionSlot::acquire(&state) else {
+        let _ = writer.send(Message::text(SERVER_FULL)).await;
+        let _ = writer.close().await;
+        return;
+    };
+
+    for banner in BANNER {
+        let _ = writer.send(Message::text(banner)).await;
+    }
+
+    // A first frame that is not a handshake is the first text command
+    let Some(first) = next_text(&mut reader).await else {
+        return;
+    };
+    let handshake = first.as_deref().ok().and_then(Protocol::from_handshake);
+    let protocol = handshake.unwrap_or(Protocol::Text);
+    let mut pending = if handshake.is_some() { None } else { Some(first) };
+
+    // Create a channel for sending messages to this client
+    let (tx, mut rx): (UnboundedSender<ServerMessage>, UnboundedReceiver<ServerMessage>) = unbounded_channel();
+
+    // Task for sending messages from the channel to the socket, one frame each
+    tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            let mut frame = protocol.render(&msg);
+            frame.pop();
+            if writer.send(Message::text(frame)).await.is_err() {
+                break;
+            }
+        }
+    });
+    if handshake.is_some() {
+        acknowledge_protocol(protocol, &tx);
+    }
+
+    loop {
+        let text = match pending.take() {
+            Some(text) => text,
+            None => match next_text(&mut reader).await {
+                Some(text) => text,
+                None => break,
+            },
+        };
+        let text = match text {
+            Ok(text) => text,
+            Err(e) => {
+                let _ = tx.send(ServerMessage::error(e));
+                continue;
+            }
+        };
+        if !handle_line(protocol, &text, &mut user_id, &state, &tx).await {
+            break;
+        }
+    }
+    handle_disconnect(&user_id, &state, &tx).await;
+}
+
+/// Waits for the next text frame, skipping pings. A binary frame is an error to send
+/// back; None once the client has closed the connection or it broke.
+async fn next_text(reader: &mut SplitStream<WebSocketStream<TcpStream>>) -> Option<Result<String, String>> {
+    while let Some(Ok(frame)) = reader.next().await {
+        match frame {
+            Message::Text(text) => return Some(Ok(text.to_string())),
+            Message::Binary(_) => return Some(Err("Only text frames are supported".to_string())),
+            Message::Close(_) => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::*;
+
+    #[tokio::test]
+    async fn test_websocket_client_speaks_json_one_message_per_frame() {
+        let (mut stream, _) = connect_async(start_gateway().await).await.unwrap();
+        assert_eq!(next_frame(&mut stream).await, BANNER[0]);
+        assert_eq!(next_frame(&mut stream).await, BANNER[1]);
+        stream.send(Message::text("PROTOCOL json")).await.unwrap();
+        assert_eq!(next_frame(&mut stream).await, r#"{"type":"ok","message":"Using the JSON protocol"}"#);
+        stream.send(Message::text(r#"{"type":"register","name":"alice","password":"hunter22"}"#)).await.unwrap();
+        let registered: serde_json::Value = serde_json::from_str(&next_frame(&mut stream).await).unwrap();
+        assert_eq!(registered["type"], "registered");
+        assert_eq!(registered["balance"], 100.0);
+        assert_eq!(registered["token"].as_str().unwrap().len(), 32);
+        stream.send(Message::text(r#"{"type":"list_tables"}"#)).await.unwrap();
+        assert_eq!(next_frame(&mut stream).await, r#"{"type":"tables","tables":[]}"#);
+    }
+
+    #[tokio::test]
+    async fn test_websocket_client_can_use_text_commands() {
+        let (mut stream, _) = connect_async(start_gateway().await).await.unwrap();
+        next_frame(&mut stream).await;
+        next_frame(&mut stream).await;
+        stream.send(Message::text("REGISTER bob hunter22")).await.unwrap();
+        assert!(next_frame(&mut stream).await.starts_with("Registered successfully. Your balance: 100\nSession token: "));
+        stream.send(Message::binary(vec![0xff, 0xfe])).await.unwrap();
+        assert_eq!(next_frame(&mut stream).await, "Only text frames are supported");
+        stream.send(Message::text("QUIT")).await.unwrap();
+        assert_eq!(next_frame(&mut stream).await, "Bye!");
+    }
+
+    #[tokio::test]
+    async fn test_actions_are_broadcast_to_the_whole_table() {
+        let url = start_gateway().await;
+        let mut clients = Vec::new();
+        for name in ["alice", "bob"] {
+            clients.push(json_client(&url, name).await);
+        }
+        clients[0].send(Message::text(r#"{"type":"create_table","table":"t"}"#)).await.unwrap();
+        for stream in clients.iter_mut() {
+            stream.send(Message::text(r#"{"type":"join_table","table":"t"}"#)).await.unwrap();
+            wait_for(stream, "joined").await;
+        }
+
+        // Once bob sits down the hand starts, and whoever is first to act is told so
+        let state: serde_json::Value = serde_json::from_str(&wait_for(&mut clients[1], r#""type":"state""#).await).unwrap();
+        let (actor, other) = if state["current_player"] == "alice" { (0, 1) } else { (1, 0) };
+        wait_for(&mut clients[actor], r#""your_turn":true"#).await;
+        clients[other].send(Message::text(r#"{"type":"fold"}"#)).await.unwrap();
+        wait_for(&mut clients[other], "Fold error: It's not your turn").await;
+        clients[actor].send(Message::text(r#"{"type":"call"}"#)).await.unwrap();
+        let acted = wait_for(&mut clients[other], "acted").await;
+        assert!(acted.contains(r#""action":{"kind":"call","amount":"#));
+        wait_for(&mut clients[other], r#""your_turn":true"#).await;
+    }
+
+    #[tokio::test]
+    async fn test_all_in_players_reach_the_runout_vote() {
+        let url = start_gateway().await;
+        let mut alice = json_client(&url, "alice").await;
+        alice.send(Message::text(r#"{"type":"create_table","table":"t"}"#)).await.unwrap();
+        alice.send(Message::text(r#"{"type":"join_table","table":"t"}"#)).await.unwrap();
+        wait_for(&mut alice, "joined").await;
+        let (mut bob, _) = connect_async(&url).await.unwrap();
+        bob.send(Message::text("REGISTER bob hunter22")).await.unwrap();
+        bob.send(Message::text("JOIN_TABLE t")).await.unwrap();
+
+        // alice speaks JSON and bob text; whoever is first to act shoves and the other calls it off
+        let state: serde_json::Value = serde_json::from_str(&wait_for(&mut alice, r#""hand_in_progress":true"#).await).unwrap();
+        let alice_first = state["current_player"] == "alice";
+        if alice_first {
+            alice.send(Message::text(r#"{"type":"all_in"}"#)).await.unwrap();
+            wait_for(&mut bob, "alice goes all-in").await;
+        }
+        bob.send(Message::text("ALL_IN")).await.unwrap();
+        wait_for(&mut bob, "You are all-in for").await;
+        if !alice_first {
+            wait_for(&mut alice, r#""action":{"kind":"all_in""#).await;
+            alice.send(Message::text(r#"{"type":"all_in"}"#)).await.unwrap();
+        }
+        wait_for(&mut alice, "You are all-in for").await;
+
+        // With nobody left to bet, the board is put to a vote
+        alice.send(Message::text(r#"{"type":"run","times":2}"#)).await.unwrap();
+        wait_for(&mut alice, "You voted to run it 2 time(s)").await;
+        bob.send(Message::text("RUN 2")).await.unwrap();
+        wait_for(&mut bob, "You voted to run it 2 time(s)").await;
+        // Whoever loses gets to muck; a winning hand is shown without asking
+        alice.send(Message::text(r#"{"type":"muck"}"#)).await.unwrap();
+        bob.send(Message::text("MUCK")).await.unwrap();
+        wait_for(&mut alice, r#""event":"showdown""#).await;
+    }
+
+    #[tokio::test]
+    async fn test_spectators_follow_the_table_without_seeing_hole_cards() {
+        let url = start_gateway().await;
+        let mut alice = json_client(&url, "alice").await;
+        alice.send(Message::text(r#"{"type":"create_table","table":"t","options":{"max_spectators":"1"}}"#)).await.unwrap();
+        wait_for(&mut alice, "table_created").await;
+        let mut carol = json_client(&url, "carol").await;
+        carol.send(Message::text(r#"{"type":"watch_table","table":"t"}"#)).await.unwrap();
+        wait_for(&mut carol, "watching").await;
+        let mut dave = json_client(&url, "dave").await;
+        dave.send(Message::text(r#"{"type":"watch_table","table":"t"}"#)).await.unwrap();
+        wait_for(&mut dave, "spectator limit").await;
+        // Carol already has the only place, so she can watch again
+        carol.send(Message::text(r#"{"type":"watch_table","table":"t"}"#)).await.unwrap();
+        wait_for(&mut carol, "watching").await;
+
+        let mut bob = json_client(&url, "bob").await;
+        for stream in [&mut alice, &mut bob] {
+            stream.send(Message::text(r#"{"type":"join_table","table":"t"}"#)).await.unwrap();
+        }
+        // The hand starts once bob sits down; carol sees it with every hole card hidden
+        let state: serde_json::Value = serde_json::from_str(&wait_for(&mut carol, r#""hidden_cards":2"#).await).unwrap();
+        let seats = state["seats"].as_array().unwrap();
+        assert_eq!(seats.len(), 2);
+        assert!(seats.iter().all(|seat| seat["hole_cards"].is_null()));
+        assert_eq!(state["your_turn"], false);
+
+        let actor = if state["current_player"] == "alice" { &mut alice } else { &mut bob };
+        actor.send(Message::text(r#"{"type":"fold"}"#)).await.unwrap();
+        wait_for(&mut carol, r#""action":{"kind":"fold"}"#).await;
+        wait_for(&mut carol, "showdown").await;
+
+        carol.send(Message::text(r#"{"type":"unwatch"}"#)).await.unwrap();
+        wait_for(&mut carol, "Stopped watching t").await;
+        dave.send(Message::text(r#"{"type":"watch_table","table":"t"}"#)).await.unwrap();
+        wait_for(&mut dave, "watching").await;
+
+        // A spectator who drops gives up their place
+        dave.close(None).await.unwrap();
+        let mut erin = json_client(&url, "erin").await;
+        let rewatch = async {
+            loop {
+                erin.send(Message::text(r#"{"type":"watch_table","table":"t"}"#)).await.unwrap();
+                let reply = next_frame(&mut erin).await;
+                if reply.contains("watching") {
+                    break;
+                }
+                assert!(reply.contains("spectator limit"), "{}", reply);
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        };
+        tokio::time::timeout(std::time::Duration::from_secs(5), rewatch).await.expect("dave's place was freed");
+    }
+}